@@ -1,111 +1,683 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, IsTerminal},
+    process::ExitCode,
+};
+
 use train_freight_system::{
-    train_freight_system::TrainFreightSystem,
-    util::{kilogram::Kilogram, minute::Minute},
+    train_freight_system::{
+        dispatch::{DeadlineDispatch, DefaultDispatch, DispatchStrategy},
+        node::{HandlingTime, Node, NodeId},
+        package::{self, Package, Priority, UnreachableReason},
+        plan::{format_time, DeliveryPlan, WaitReason},
+        train::{self, Timetable, Train},
+        TrainFreightSystem,
+    },
+    util::{datetime::DateTime, kilogram::Kilogram, minute::Minute, volume::Liter},
 };
 
+fn show_usage() {
+    println!("Usage: train_freight_system [--strict] [SCRIPT]");
+    println!("Reads commands from SCRIPT, or from stdin when it is not given.");
+    println!("[--strict] stop at the first invalid command and exit with an error");
+}
+
 fn show_options() {
     println!("Select options below");
     println!("[N] Node input [ ex: N,A where A=name]");
+    println!("    optional platform count, trains stopped at once [ ex: N,A,2 ]");
     println!("[E] Edge input [ ex: E,E1,A,B,30 where E1=name, A=node1, B=node2, 30=travel time]");
+    println!("    optional track count, trains on the edge at once [ ex: E,E1,A,B,30,1 ]");
+    println!("    parallel edges between the same nodes are kept apart [ ex: E,X1,A,B,10 next to E,E1,A,B,30 ]");
+    println!("[D] Directed edge input, one-way from node1 to node2 [ ex: D,E1,A,B,40 then D,E1,B,A,20 for a different time back ]");
+    println!("[H] Handling time input [ ex: H,A,5,1,0 where A=node, 5=per stop, 1=per package, 0=per tonne]");
     println!("[T] Train input [ ex: T,Q1,6,B where Q1=name, 6=Capacity, B=node location]");
+    println!("    optional volume capacity in liters [ ex: T,Q1,6,B,500 ]");
+    println!("    optional earliest departure time [ ex: T,Q1,6,B,,30 or T,Q1,6,B,,2024-05-01 09:00 ]");
+    println!("[Y] Timetable of a scheduled train [ ex: Y,Q1,A,6h,B,6h30m,C,6h40m where Q1 leaves A at 6h and B at 6h30m, due at C at 6h40m ]");
+    println!("    optional period, the service runs again from its first stop [ ex: Y,Q1,A,6h,B,6h30m,A,7h30m,1d ], Y,Q1 to dispatch it on demand again");
     println!("[P] Package input [ ex: P,K1,5,A,C where K1=name 5=Weight, A=node origin, B=node destination]");
-    println!("[X] deliver packages");
+    println!("    optional pickup time and deadline [ ex: P,K1,5,A,C,10,90 or P,K1,5,A,C,,90 ]");
+    println!("    optional priority EXPRESS, STANDARD or ECONOMY [ ex: P,K1,5,A,C,,,EXPRESS ]");
+    println!("    optional volume in liters [ ex: P,K1,5,A,C,,,,300 ]");
+    println!("[R] Remove input [ ex: R,N,A or R,E,E1 or R,T,Q1 or R,P,K1 ]");
+    println!("    FORCE to remove a node with its trains and packages, or a train with its packages [ ex: R,N,A,FORCE ]");
+    println!("[U] Update input [ ex: U,E,E1,40 travel time, U,T,Q1,10 capacity or U,P,K1,3 weight ]");
+    println!("    station a one-way edge leaves from, needed when both of its ways are one-way [ ex: U,E,E1,20,B ]");
+    println!("[L] List entries [ L,N nodes with their edges, L,T trains, L,P packages or L for all ]");
+    println!("[S] Shortest route [ ex: S,A,C from A to C ]");
+    println!("[G] Graphviz DOT file of the network [ ex: G,network.dot or G,network.dot,TRAINS with the trains and packages at each node ]");
+    println!("[W] Start time of the next delivery run, times can then be given as dates and times [ ex: W,2024-05-01 08:30 or W to count minutes only ]");
+    println!("[X] deliver packages [ X,DEADLINE to send trains for the packages closest to their deadline first ]");
+    println!("    optional SVG timeline of the train moves [ ex: X,,plan.svg or X,DEADLINE,plan.svg ]");
+    println!("Weights, times and volumes take units [ ex: 2t or 500kg, 1h30m or 2d, 2m3 or 300l ], plain numbers are kg, minutes and liters");
+    println!("    times can also be a clock time on the day the run starts, at midnight without W [ ex: 06:30 ], or a date and time after W [ ex: 2024-05-01 06:30 ]");
     println!("[C] Clear data");
+    println!("[Q] Quit");
     println!("[_]Any invalid keys will show the options");
 }
 
-fn main() {
-    let stdin = std::io::stdin();
-    let mut system = TrainFreightSystem::default();
-    show_options();
-    loop {
-        let mut input = String::new();
-        stdin.read_line(&mut input).expect("Unable to read input");
-        input = input.to_uppercase();
-        match &input[..1] {
-            "N" => {
-                let node: Vec<&str> = input.split(',').map(|str| str.trim()).collect();
-                if node.len() == 2 && !node[1].is_empty() {
-                    system
-                        .add_node(node[1])
-                        .unwrap_or_else(|err| println!("{:?}", err));
+fn show_delivery_plan(plan: &DeliveryPlan) {
+    let start = plan.start.as_ref();
+    for train_move in &plan.moves {
+        let picked_up: Vec<&str> = train_move
+            .picked_up
+            .iter()
+            .map(|package_id| package_id.0.as_str())
+            .collect();
+        let delivered: Vec<String> = plan
+            .list_packages_delivered_by(train_move)
+            .into_iter()
+            .map(|package_id| package_id.0)
+            .collect();
+        println!(
+            "{}, T={}, N1={}, P1={:?}, N2={}, P2 ={:?}, E={}",
+            format_time(start, &train_move.departure),
+            train_move.train.0,
+            train_move.origin.0,
+            picked_up,
+            train_move.destination.0,
+            delivered,
+            train_move.edge.0
+        );
+    }
+    for wait in &plan.waits {
+        match wait.reason {
+            WaitReason::TrackTaken => println!(
+                "T={} held at {} for {} from {} until {}",
+                wait.train.0,
+                wait.station.0,
+                wait.edge.0,
+                format_time(start, &wait.from),
+                format_time(start, &wait.until)
+            ),
+            WaitReason::StationFull => println!(
+                "T={} queued on {} for a platform at {} from {} until {}",
+                wait.train.0,
+                wait.edge.0,
+                wait.station.0,
+                format_time(start, &wait.from),
+                format_time(start, &wait.until)
+            ),
+            WaitReason::NotDeparted => println!(
+                "T={} held at {} for its departure from {} until {}",
+                wait.train.0,
+                wait.station.0,
+                format_time(start, &wait.from),
+                format_time(start, &wait.until)
+            ),
+        }
+    }
+    for delivery in plan.list_late_deliveries() {
+        println!(
+            "{} delivered at {}, late by {}",
+            delivery.package.0,
+            format_time(start, &delivery.time),
+            delivery.lateness.as_ref().unwrap().0
+        );
+    }
+    for class in plan.list_delivery_times_by_priority() {
+        println!(
+            "{:?}: {} delivered, average W={}, last W={}",
+            class.priority, class.delivered, class.average_time.0, class.latest_time.0
+        );
+    }
+    println!("completed delivery in: {:?}", plan.total_time);
+    if let Some(end) = plan.clock_time(&plan.total_time) {
+        println!("completed delivery at: {end}");
+    }
+}
+
+fn show_undeliverable_packages(system: &TrainFreightSystem) {
+    for package in &system.package_handler.packages {
+        match &package.status {
+            package::Status::CantBeTransported(_) => {
+                println!(
+                    "{} can't be delivered: too heavy or too bulky for every train",
+                    package.id.0
+                )
+            }
+            package::Status::Unreachable(_, UnreachableReason::NoRoute) => {
+                println!(
+                    "{} can't be delivered: no route to {}",
+                    package.id.0, package.destination.0
+                )
+            }
+            package::Status::Unreachable(_, UnreachableReason::NoTrain) => {
+                println!(
+                    "{} can't be delivered: no train can take it there",
+                    package.id.0
+                )
+            }
+            _ => {}
+        }
+    }
+}
+
+fn show_node(node: &Node) {
+    let edges: Vec<String> = node
+        .edges
+        .iter()
+        .map(|edge| {
+            let tracks = match edge.tracks {
+                Some(tracks) => format!(" on {tracks} track(s)"),
+                None => String::new(),
+            };
+            format!("{} to {} in {}{}", edge.id.0, edge.node.0, edge.travel_time.0, tracks)
+        })
+        .collect();
+    let mut line = format!("N={}, E={:?}", node.id.0, edges);
+    if let Some(platforms) = node.platforms {
+        line += &format!(", platforms={platforms}");
+    }
+    let handling_time = &node.handling_time;
+    if *handling_time != HandlingTime::default() {
+        line += &format!(
+            ", handling={}/{}/{}",
+            handling_time.per_stop.0, handling_time.per_package.0, handling_time.per_tonne.0
+        );
+    }
+    println!("{line}");
+}
+
+fn show_train(system: &TrainFreightSystem, train: &Train) {
+    let status = match &train.status {
+        train::Status::NotAvailable => "not available".to_string(),
+        train::Status::StoppedAt(node_id) => format!("at {}", node_id.0),
+        train::Status::DeliveringTo(origin, destination, edge_id, arrival) => {
+            format!(
+                "from {} to {} on {}, arriving {}",
+                origin.0,
+                destination.0,
+                edge_id.0,
+                format_time(system.get_start(), arrival)
+            )
+        }
+        train::Status::QueuedFor(origin, destination, edge_id) => {
+            format!(
+                "from {} on {} queued at {}",
+                origin.0, edge_id.0, destination.0
+            )
+        }
+    };
+    let mut details = String::new();
+    if let Some(max_volume) = &train.max_volume {
+        details += &format!(", volume={}/{}", train.load_volume.0, max_volume.0);
+    }
+    if let Some(departure) = &train.earliest_departure {
+        details += &format!(", departs {}", format_time(system.get_start(), departure));
+    }
+    if let Some(leg) = train.timetable.as_ref().and_then(Timetable::next_leg) {
+        details += &format!(
+            ", scheduled from {} to {} leaving {}",
+            leg.origin.0,
+            leg.destination.0,
+            format_time(system.get_start(), &leg.departure)
+        );
+    }
+    println!(
+        "T={}, {}, load={}/{}{}, P={:?}",
+        train.id.0,
+        status,
+        train.load_size.0,
+        train.max_capacity.0,
+        details,
+        system.package_handler.list_package_names_in_transit(&train.id)
+    );
+}
+
+fn show_package(system: &TrainFreightSystem, package: &Package) {
+    let status = match &package.status {
+        package::Status::Completed => "completed".to_string(),
+        package::Status::DroppedAt(node_id, _) => format!("at {}", node_id.0),
+        package::Status::LoadedTo(train_id) => format!("on {}", train_id.0),
+        package::Status::Delivered(train_id) => format!("delivered by {}", train_id.0),
+        package::Status::CantBeTransported(node_id) => {
+            format!("too heavy or too bulky at {}", node_id.0)
+        }
+        package::Status::Unreachable(node_id, _) => format!("unreachable at {}", node_id.0),
+        package::Status::NotReady(node_id) => format!("not ready at {}", node_id.0),
+    };
+    let mut line = format!(
+        "P={}, weight={}, N1={}, N2={}, {}",
+        package.id.0, package.weight.0, package.origin.0, package.destination.0, status
+    );
+    if let Some(volume) = &package.volume {
+        line += &format!(", volume={}", volume.0);
+    }
+    if let Some(earliest_pickup) = &package.earliest_pickup {
+        let earliest_pickup = format_time(system.get_start(), earliest_pickup);
+        line += &format!(", pickup {earliest_pickup}");
+    }
+    if let Some(deadline) = &package.deadline {
+        let deadline = format_time(system.get_start(), deadline);
+        line += &format!(", deadline {deadline}");
+    }
+    if package.priority != Priority::Standard {
+        line += &format!(", {:?}", package.priority);
+    }
+    println!("{line}");
+}
+
+// Minutes from the start of the run, a clock time on the day it starts or
+// a date and time from the start given
+fn parse_time(system: &TrainFreightSystem, field: &str) -> Option<Minute> {
+    if let Ok(time) = field.parse::<Minute>() {
+        return Some(time);
+    }
+    let Ok(time) = field.parse() else {
+        // Without a start the run starts at midnight
+        let start = system.get_start().cloned().unwrap_or_default();
+        return start.at_clock_time(field).ok()?.minutes_since(&start);
+    };
+    system.minutes_from_start(&time)
+}
+
+enum Command {
+    Done,
+    Quit,
+    Invalid(String),
+    Unknown(String),
+}
+
+fn run_command(system: &mut TrainFreightSystem, line: &str) -> Command {
+    let input = line.trim().to_uppercase();
+    // Blank lines and comments in scripts
+    if input.is_empty() || input.starts_with('#') {
+        return Command::Done;
+    }
+
+    let fields: Vec<&str> = input.split(',').map(|str| str.trim()).collect();
+    let result = match fields[0] {
+        "N" => {
+            if (2..=3).contains(&fields.len()) && !fields[1].is_empty() {
+                // Checked before the node is added so that a refused count
+                // doesn't leave it behind
+                let platforms = match fields.get(2) {
+                    None => None,
+                    Some(field) => match field.parse::<u32>() {
+                        Ok(platforms) if platforms > 0 => Some(platforms),
+                        _ => return Command::Invalid("Invalid platform count".into()),
+                    },
+                };
+                system.add_node(fields[1]).and_then(|()| match platforms {
+                    Some(platforms) => system.set_node_platforms(fields[1], platforms),
+                    None => Ok(()),
+                })
+            } else {
+                return Command::Invalid("Invalid node entry".into());
+            }
+        }
+        "E" | "D" => {
+            if (5..=6).contains(&fields.len()) && !fields[1].is_empty() {
+                let Ok(time) = fields[4].parse::<Minute>() else {
+                    return Command::Invalid("Invalid travel time".into());
+                };
+                // Checked before the edge is added so that a refused count
+                // doesn't leave it behind
+                let tracks = match fields.get(5) {
+                    None => None,
+                    Some(field) => match field.parse::<u32>() {
+                        Ok(tracks) if tracks > 0 => Some(tracks),
+                        _ => return Command::Invalid("Invalid track count".into()),
+                    },
+                };
+                let added = if fields[0] == "D" {
+                    system.add_directed_edge(fields[1], fields[2], fields[3], time)
                 } else {
-                    println!("Invalid node entry")
+                    system.add_edge(fields[1], fields[2], fields[3], time)
+                };
+                added.and_then(|()| match tracks {
+                    Some(tracks) => system.set_edge_tracks(fields[1], tracks),
+                    None => Ok(()),
+                })
+            } else {
+                return Command::Invalid("Invalid edge entry".into());
+            }
+        }
+        "H" => {
+            if fields.len() == 5 && !fields[1].is_empty() {
+                let times: Vec<Minute> = fields[2..]
+                    .iter()
+                    .filter_map(|field| field.parse::<Minute>().ok())
+                    .collect();
+                let [per_stop, per_package, per_tonne] = &times[..] else {
+                    return Command::Invalid("Invalid handling time".into());
+                };
+                let handling_time = HandlingTime {
+                    per_stop: per_stop.clone(),
+                    per_package: per_package.clone(),
+                    per_tonne: per_tonne.clone(),
+                };
+                system.set_node_handling_time(fields[1], handling_time)
+            } else {
+                return Command::Invalid("Invalid handling time entry".into());
+            }
+        }
+        "T" => {
+            if (4..=6).contains(&fields.len()) && !fields[1].is_empty() {
+                let Ok(capacity) = fields[2].parse::<Kilogram>() else {
+                    return Command::Invalid("Invalid capacity".into());
+                };
+                let volume = match fields.get(4) {
+                    None | Some(&"") => None,
+                    Some(field) => match field.parse::<Liter>() {
+                        Ok(volume) => Some(volume),
+                        Err(_) => return Command::Invalid("Invalid volume".into()),
+                    },
+                };
+                let departure = match fields.get(5) {
+                    None => None,
+                    Some(field) => match parse_time(system, field) {
+                        Some(departure) => Some(departure),
+                        None => return Command::Invalid("Invalid departure time".into()),
+                    },
+                };
+                system
+                    .add_train(fields[1], capacity, fields[3])
+                    .and_then(|()| {
+                        // Taken back out when a setting is refused, so that the
+                        // corrected entry can be given again
+                        system
+                            .set_train_volume(fields[1], volume)
+                            .and_then(|()| system.set_train_departure(fields[1], departure))
+                            .inspect_err(|_| {
+                                let _ = system.remove_train(fields[1], true);
+                            })
+                    })
+            } else {
+                return Command::Invalid("Invalid train entry".into());
+            }
+        }
+        "Y" => {
+            if fields.len() >= 2 && !fields[1].is_empty() {
+                // Station and time of each stop, then the period when it
+                // repeats
+                let mut stops = vec![];
+                let mut period = None;
+                for stop in fields[2..].chunks(2) {
+                    match stop {
+                        [station, time] => {
+                            let Some(time) = parse_time(system, time) else {
+                                return Command::Invalid("Invalid stop time".into());
+                            };
+                            stops.push((NodeId(station.to_string()), time));
+                        }
+                        [field] => match field.parse::<Minute>() {
+                            Ok(time) => period = Some(time),
+                            Err(_) => return Command::Invalid("Invalid period".into()),
+                        },
+                        _ => unreachable!(),
+                    }
                 }
+                if stops.is_empty() && period.is_some() {
+                    return Command::Invalid("Invalid timetable entry".into());
+                }
+                let timetable = (!stops.is_empty()).then(|| Timetable::new(stops, period));
+                system.set_train_timetable(fields[1], timetable)
+            } else {
+                return Command::Invalid("Invalid timetable entry".into());
             }
-            "E" => {
-                let edge: Vec<&str> = input.split(',').map(|str| str.trim()).collect();
-                if edge.len() == 5 && !edge[1].is_empty() {
-                    let time = if let Some(time) = edge[4].clone().parse::<u32>().ok() {
-                        time
-                    } else {
-                        println!("Invalid travel time");
+        }
+        "P" => {
+            if (5..=9).contains(&fields.len()) && !fields[1].is_empty() {
+                let Ok(weight) = fields[2].parse::<Kilogram>() else {
+                    return Command::Invalid("Invalid weight".into());
+                };
+                // Optional earliest pickup and deadline, left empty to skip
+                let mut times = [None, None];
+                for (index, field) in fields[5..fields.len().min(7)].iter().enumerate() {
+                    if field.is_empty() {
                         continue;
+                    }
+                    let Some(time) = parse_time(system, field) else {
+                        return Command::Invalid("Invalid pickup or deadline time".into());
                     };
-                    system
-                        .add_edge(edge[1], edge[2], edge[3], Minute(time))
-                        .unwrap_or_else(|err| println!("{:?}", err));
-                } else {
-                    println!("Invalid edge entry");
+                    times[index] = Some(time);
                 }
+                let priority = match fields.get(7) {
+                    None | Some(&"") | Some(&"STANDARD") => Priority::Standard,
+                    Some(&"EXPRESS") => Priority::Express,
+                    Some(&"ECONOMY") => Priority::Economy,
+                    Some(_) => return Command::Invalid("Invalid priority".into()),
+                };
+                let volume = match fields.get(8) {
+                    None | Some(&"") => None,
+                    Some(field) => match field.parse::<Liter>() {
+                        Ok(volume) => Some(volume),
+                        Err(_) => return Command::Invalid("Invalid volume".into()),
+                    },
+                };
+                system
+                    .add_package(fields[1], weight, fields[3], fields[4])
+                    .and_then(|()| {
+                        // Taken back out when a setting is refused, as for
+                        // trains
+                        system
+                            .set_package_time_window(fields[1], times[0].clone(), times[1].clone())
+                            .and_then(|()| system.set_package_priority(fields[1], priority))
+                            .and_then(|()| system.set_package_volume(fields[1], volume))
+                            .inspect_err(|_| {
+                                let _ = system.remove_package(fields[1]);
+                            })
+                    })
+            } else {
+                return Command::Invalid("Invalid package entry".into());
             }
-            "T" => {
-                let train: Vec<&str> = input.split(',').map(|str| str.trim()).collect();
-                if train.len() == 4 && !train[1].is_empty() {
-                    let capacity = if let Some(capacity) = train[2].clone().parse::<u32>().ok() {
-                        capacity
-                    } else {
-                        println!("Invalid capacity");
-                        continue;
-                    };
-                    system
-                        .add_train(train[1], Kilogram(capacity), train[3])
-                        .unwrap_or_else(|err| println!("{:?}", err));
-                } else {
-                    println!("Invalid train entry");
+        }
+        "R" => {
+            let force = match fields.get(3) {
+                None => false,
+                Some(&"FORCE") => true,
+                Some(_) => return Command::Invalid("Invalid remove entry".into()),
+            };
+            if (3..=4).contains(&fields.len()) && !fields[2].is_empty() {
+                match fields[1] {
+                    "N" => system.remove_node(fields[2], force),
+                    "E" if !force => system.remove_edge(fields[2]),
+                    "T" => system.remove_train(fields[2], force),
+                    "P" if !force => system.remove_package(fields[2]),
+                    _ => return Command::Invalid("Invalid remove entry".into()),
                 }
+            } else {
+                return Command::Invalid("Invalid remove entry".into());
             }
-            "P" => {
-                let package: Vec<&str> = input.split(',').map(|str| str.trim()).collect();
-                if package.len() == 5 && !package[1].is_empty() {
-                    let weight = if let Some(weight) = package[2].clone().parse::<u32>().ok() {
-                        weight
-                    } else {
-                        println!("Invalid weight");
-                        continue;
-                    };
-                    system
-                        .add_package(package[1], Kilogram(weight), package[3], package[4])
-                        .unwrap_or_else(|err| println!("{:?}", err));
-                } else {
-                    println!("Invalid package entry");
+        }
+        "U" => {
+            // Only a one-way edge takes the station it leaves from
+            let origin = fields.get(4).copied();
+            if (4..=5).contains(&fields.len()) && !fields[2].is_empty() {
+                let value = fields[3];
+                let updated = match fields[1] {
+                    "E" => value
+                        .parse()
+                        .map(|time| system.set_edge_travel_time(fields[2], origin, time)),
+                    _ if origin.is_some() => {
+                        return Command::Invalid("Invalid update entry".into())
+                    }
+                    "T" => value
+                        .parse()
+                        .map(|capacity| system.set_train_capacity(fields[2], capacity)),
+                    "P" => value
+                        .parse()
+                        .map(|weight| system.set_package_weight(fields[2], weight)),
+                    _ => return Command::Invalid("Invalid update entry".into()),
+                };
+                let Ok(updated) = updated else {
+                    return Command::Invalid("Invalid value".into());
+                };
+                updated
+            } else {
+                return Command::Invalid("Invalid update entry".into());
+            }
+        }
+        "L" => {
+            let (nodes, trains, packages) = match fields[1..] {
+                [] => (true, true, true),
+                ["N"] => (true, false, false),
+                ["T"] => (false, true, false),
+                ["P"] => (false, false, true),
+                _ => return Command::Invalid("Invalid list entry".into()),
+            };
+            if nodes {
+                system.nodes.iter().for_each(show_node);
+            }
+            if trains {
+                for train in &system.train_handler.trains {
+                    show_train(system, train);
+                }
+            }
+            if packages {
+                for package in &system.package_handler.packages {
+                    show_package(system, package);
                 }
             }
-            "X" => {
-                let total_delivery_time = system.deliver_packages();
-                println!("completed delivery in: {:?}", total_delivery_time);
+            Ok(())
+        }
+        "S" => {
+            if fields.len() == 3 {
+                system.find_route(fields[1], fields[2]).map(|route| match route {
+                    Some(route) => {
+                        let mut line = route.nodes[0].0.clone();
+                        for (edge_id, node_id) in route.edges.iter().zip(&route.nodes[1..]) {
+                            line += &format!(" -{}-> {}", edge_id.0, node_id.0);
+                        }
+                        println!("{line} in {}", route.travel_time.0);
+                    }
+                    None => println!("No route from {} to {}", fields[1], fields[2]),
+                })
+            } else {
+                return Command::Invalid("Invalid route entry".into());
             }
-            "C" => {
-                system = TrainFreightSystem::default();
-                println!("Cleared");
+        }
+        "G" => {
+            // File names keep their case
+            let path = line.split(',').nth(1).unwrap_or_default().trim();
+            let positions = match fields.get(2) {
+                None => false,
+                Some(&"TRAINS") => true,
+                Some(_) => return Command::Invalid("Invalid graph entry".into()),
+            };
+            if (2..=3).contains(&fields.len()) && !path.is_empty() {
+                system.save_dot(path, positions).map(|()| println!("Saved to {path}"))
+            } else {
+                return Command::Invalid("Invalid graph entry".into());
             }
-            _ => {
-                show_options();
+        }
+        "X" => {
+            if fields.len() > 3 {
+                return Command::Invalid("Invalid deliver entry".into());
             }
+            let mut strategy: Box<dyn DispatchStrategy> = match fields.get(1) {
+                None | Some(&"") => Box::new(DefaultDispatch),
+                Some(&"DEADLINE") => Box::new(DeadlineDispatch),
+                Some(_) => return Command::Invalid("Invalid dispatch mode".into()),
+            };
+            // Optional timeline file, keeping its case
+            let path = line.split(',').nth(2).map(str::trim);
+            system.deliver_packages(strategy.as_mut()).and_then(|plan| {
+                show_delivery_plan(&plan);
+                show_undeliverable_packages(system);
+                match path {
+                    Some(path) if !path.is_empty() => {
+                        plan.save_svg(path).map(|()| println!("Timeline saved to {path}"))
+                    }
+                    _ => Ok(()),
+                }
+            })
+        }
+        "W" => {
+            let start = match fields.get(1) {
+                None => None,
+                Some(field) => match field.parse::<DateTime>() {
+                    Ok(start) => Some(start),
+                    Err(err) => return Command::Invalid(err.to_string()),
+                },
+            };
+            if fields.len() > 2 {
+                return Command::Invalid("Invalid start entry".into());
+            }
+            system.set_start(start);
+            Ok(())
+        }
+        "C" => {
+            *system = TrainFreightSystem::default();
+            println!("Cleared");
+            Ok(())
+        }
+        "Q" => return Command::Quit,
+        _ => return Command::Unknown(format!("Invalid command '{input}'")),
+    };
+
+    match result {
+        Ok(()) => Command::Done,
+        Err(err) => Command::Invalid(err.to_string()),
+    }
+}
+
+// Runs the commands until the end of the input or `Q`. Interactive sessions
+// show the options on unknown commands, strict ones stop at the first
+// invalid one.
+fn run(input: impl BufRead, interactive: bool, strict: bool) -> ExitCode {
+    let mut system = TrainFreightSystem::default();
+    if interactive {
+        show_options();
+    }
+
+    for (line_number, line) in input.lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                eprintln!("Unable to read input: {err}");
+                return ExitCode::FAILURE;
+            }
+        };
+
+        match run_command(&mut system, &line) {
+            Command::Done => {}
+            Command::Quit => break,
+            Command::Invalid(msg) | Command::Unknown(msg) if strict => {
+                eprintln!("line {}: {msg}", line_number + 1);
+                return ExitCode::FAILURE;
+            }
+            Command::Unknown(_) if interactive => show_options(),
+            Command::Invalid(msg) | Command::Unknown(msg) => println!("{msg}"),
         }
     }
 
-    // let system = system
-    //     .node("A")?
-    //     .node("B")?
-    //     .node("C")?
-    //     .node("D")?
-    //     .edge("E1", "A", "B", Minute(30))?
-    //     .edge("E2", "B", "C", Minute(10))?
-    //     .edge("E3", "C", "D", Minute(20))?
-    //     .package("K1", Kilogram(5), "C", "A")?
-    //     .package("K2", Kilogram(4), "D", "A")?
-    //     .train("Q1", Kilogram(10), "B")?;
+    ExitCode::SUCCESS
+}
+
+fn main() -> ExitCode {
+    let mut strict = false;
+    let mut script = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--strict" => strict = true,
+            "-h" | "--help" => {
+                show_usage();
+                return ExitCode::SUCCESS;
+            }
+            _ if script.is_none() => script = Some(arg),
+            _ => {
+                show_usage();
+                return ExitCode::FAILURE;
+            }
+        }
+    }
 
-    // let total_delivery_time = system.deliver_packages();
+    match script {
+        Some(path) => match File::open(&path) {
+            Ok(file) => run(BufReader::new(file), false, strict),
+            Err(err) => {
+                eprintln!("Unable to open '{path}': {err}");
+                ExitCode::FAILURE
+            }
+        },
+        None => {
+            let stdin = io::stdin();
+            let interactive = stdin.is_terminal();
+            run(stdin.lock(), interactive, strict)
+        }
+    }
 }
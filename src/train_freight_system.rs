@@ -1,46 +1,121 @@
-use crate::util::{kilogram::Kilogram, minute::Minute};
+use std::{cell::OnceCell, path::Path};
+
+use crate::util::{datetime::DateTime, kilogram::Kilogram, minute::Minute, volume::Liter};
 
 use self::{
-    edge::Edge,
-    error::{Error, ErrorKind, Result},
-    node::{Node, NodeId},
-    package::{PackageHandler, PackageId, Package},
-    train::TrainHandler,
+    dispatch::{DispatchStrategy, TrainAction},
+    edge::{Edge, EdgeId},
+    error::{Error, Result},
+    node::{HandlingTime, Node, NodeId},
+    package::{Package, PackageHandler, PackageId, Priority, UnreachableReason},
+    plan::{DeliveryPlan, TrainMove, TrainWait, WaitReason},
+    route::{Route, RouteTable},
+    scenario::{EdgeScenario, HandlingTimeScenario, PlatformsScenario, Scenario},
+    simulation::{Event, Simulation},
+    train::{Leg, Timetable, Train, TrainHandler, TrainId},
 };
 
-pub(crate) mod edge;
+pub mod dispatch;
+pub mod dot;
+pub mod edge;
 pub mod error;
-pub(crate) mod node;
-pub(crate) mod package;
-pub(crate) mod train;
+pub mod node;
+pub mod package;
+pub mod plan;
+pub mod route;
+pub mod scenario;
+pub mod simulation;
+pub mod solver;
+pub mod timeline;
+pub mod train;
 
 #[derive(Debug)]
 pub enum DeliveryResult {
     NoPackages,
     NoTrains,
+    NoRoutes,
     NotAllPackageLoaded,
     AllPackageLoaded,
     TrainPicking,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct TrainFreightSystem {
     pub nodes: Vec<Node>,
     pub train_handler: TrainHandler,
     pub package_handler: PackageHandler,
+    // Built on first use, cleared whenever the nodes or edges change
+    route_table: OnceCell<RouteTable>,
+    // Clock time the next delivery run starts at
+    start: Option<DateTime>,
+    // Packages booked on a scheduled train for the delivery run under way
+    bookings: Vec<(PackageId, TrainId)>,
 }
 
+// Scheduled train with its upcoming legs and the weight and volume taken on
+// each of them
+type Service<'a> = (&'a Train, Vec<Leg>, Vec<(Kilogram, Liter)>);
+
 impl TrainFreightSystem {
     pub fn add_node(&mut self, name: &str) -> Result<()> {
         if self.find_node_index_by_name(name).is_some() {
-            return Err(Error::new(
-                ErrorKind::AddStationError,
-                format!("Station '{name}' already existing"),
-            )
-            .into());
+            return Err(Error::DuplicateNode(NodeId(name.into())));
         }
 
         self.nodes.push(Node::new(name));
+        self.route_table.take();
+        Ok(())
+    }
+
+    /// Removes the station and the edges to it. Trains and packages at the
+    /// station, or on their way to or from it, and trains with a timetable
+    /// calling at it are removed along with it when `force` is set,
+    /// otherwise the station is kept.
+    pub fn remove_node(&mut self, name: &str, force: bool) -> Result<()> {
+        let pos = self.find_existing_node_index(name)?;
+        let node_id = self.nodes[pos].id.clone();
+        let train_ids: Vec<TrainId> = self
+            .train_handler
+            .trains
+            .iter()
+            .filter(|train| {
+                train.list_stations().contains(&&node_id)
+                    || train
+                        .timetable
+                        .as_ref()
+                        .is_some_and(|timetable| timetable.calls_at(&node_id))
+            })
+            .map(|train| train.id.clone())
+            .collect();
+        let packages: Vec<&Package> = self
+            .package_handler
+            .packages
+            .iter()
+            .filter(|package| package.uses_node(&node_id))
+            .collect();
+        // Delivered packages go without asking
+        if !force
+            && (!train_ids.is_empty() || packages.iter().any(|package| !package.is_delivered()))
+        {
+            return Err(Error::NodeInUse(node_id));
+        }
+
+        let package_ids: Vec<PackageId> =
+            packages.iter().map(|package| package.id.clone()).collect();
+        for train_id in train_ids {
+            self.remove_train(&train_id.0, true)?;
+        }
+        for package_id in package_ids {
+            // Gone with a train removed above
+            if self.package_handler.get_package(&package_id).is_some() {
+                self.remove_package(&package_id.0)?;
+            }
+        }
+        for node in self.nodes.iter_mut() {
+            node.edges.retain(|edge| edge.node != node_id);
+        }
+        self.nodes.remove(pos);
+        self.route_table.take();
         Ok(())
     }
 
@@ -52,6 +127,11 @@ impl TrainFreightSystem {
         self.nodes.iter().position(|node| node.id == *node_id)
     }
 
+    fn find_existing_node_index(&self, node_name: &str) -> Result<usize> {
+        self.find_node_index_by_name(node_name)
+            .ok_or_else(|| Error::UnknownNode(NodeId(node_name.into())))
+    }
+
     pub fn add_edge(
         &mut self,
         name: &str,
@@ -59,26 +139,11 @@ impl TrainFreightSystem {
         node_2: &str,
         travel_time: Minute,
     ) -> Result<()> {
-        let node_1_pos = self.find_node_index_by_name(node_1).ok_or_else(|| {
-            Error::new(
-                ErrorKind::AddEdgeError,
-                format!("Node1 '{node_1}' non-existent"),
-            )
-        })?;
-
-        let node_2_pos = self.find_node_index_by_name(node_2).ok_or_else(|| {
-            Error::new(
-                ErrorKind::AddEdgeError,
-                format!("Node2 '{node_2}' non-existent"),
-            )
-        })?;
+        let node_1_pos = self.find_existing_node_index(node_1)?;
+        let node_2_pos = self.find_existing_node_index(node_2)?;
 
         if node_1_pos == node_2_pos {
-            return Err(Error::new(
-                ErrorKind::AddEdgeError,
-                "Node1 and Node2 are the same".to_string(),
-            )
-            .into());
+            return Err(Error::SelfLoop(EdgeId(name.into())));
         }
 
         // Push edges on both sides
@@ -88,22 +153,279 @@ impl TrainFreightSystem {
             travel_time.clone(),
         ))?;
         self.nodes[node_2_pos].add_edge(Edge::new(name, NodeId(node_1.into()), travel_time))?;
+        self.route_table.take();
+        Ok(())
+    }
+
+    /// One-way edge from `origin` to `destination`, add another one the other
+    /// way, under the same name to share its tracks, for a different travel
+    /// time back.
+    pub fn add_directed_edge(
+        &mut self,
+        name: &str,
+        origin: &str,
+        destination: &str,
+        travel_time: Minute,
+    ) -> Result<()> {
+        let origin_pos = self.find_existing_node_index(origin)?;
+        let destination_pos = self.find_existing_node_index(destination)?;
+
+        if origin_pos == destination_pos {
+            return Err(Error::SelfLoop(EdgeId(name.into())));
+        }
+
+        let destination_id = self.nodes[destination_pos].id.clone();
+        self.nodes[origin_pos].add_edge(Edge::new(name, destination_id, travel_time))?;
+        self.route_table.take();
+        Ok(())
+    }
+
+    /// Removes the edge from both of its stations, or both ways of a one-way
+    /// edge added under the same name.
+    pub fn remove_edge(&mut self, name: &str) -> Result<()> {
+        let edge_id = EdgeId(name.into());
+        let has_edge = self
+            .nodes
+            .iter()
+            .any(|node| node.find_edge_by_id(&edge_id).is_some());
+        if !has_edge {
+            return Err(Error::UnknownEdge(edge_id));
+        }
+        let is_in_use = self
+            .train_handler
+            .trains
+            .iter()
+            .any(|train| train.get_edge() == Some(&edge_id));
+        if is_in_use {
+            return Err(Error::EdgeInUse(edge_id));
+        }
+
+        for node in self.nodes.iter_mut() {
+            node.edges.retain(|edge| edge.id != edge_id);
+        }
+        self.route_table.take();
+        Ok(())
+    }
+
+    /// A two-way edge takes the new travel time both ways, a one-way edge only
+    /// out of `origin`, which can be left out unless both ways of the edge are
+    /// one-way.
+    pub fn set_edge_travel_time(
+        &mut self,
+        name: &str,
+        origin: Option<&str>,
+        travel_time: Minute,
+    ) -> Result<()> {
+        let edge_id = EdgeId(name.into());
+        let ways: Vec<(usize, &Edge)> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(pos, node)| Some((pos, node.find_edge_by_id(&edge_id)?)))
+            .collect();
+        if ways.is_empty() {
+            return Err(Error::UnknownEdge(edge_id));
+        }
+        let origin_pos = match origin {
+            Some(origin) => {
+                let pos = self.find_existing_node_index(origin)?;
+                if !ways.iter().any(|&(way, _)| way == pos) {
+                    let from = self.nodes[pos].id.clone();
+                    return Err(Error::NoEdge {
+                        from,
+                        edge: edge_id,
+                    });
+                }
+                Some(pos)
+            }
+            None => None,
+        };
+        // Same way back as in `to_scenario`
+        let is_two_way = matches!(ways[..], [(pos_1, edge_1), (pos_2, edge_2)]
+            if edge_1.node == self.nodes[pos_2].id
+                && edge_2.node == self.nodes[pos_1].id
+                && edge_1.travel_time == edge_2.travel_time);
+        let updated: Vec<usize> = match (origin_pos, &ways[..]) {
+            _ if is_two_way => ways.iter().map(|&(pos, _)| pos).collect(),
+            (Some(pos), _) => vec![pos],
+            (None, [(pos, _)]) => vec![*pos],
+            (None, _) => return Err(Error::OneWayEdge(edge_id)),
+        };
+
+        for pos in updated {
+            if let Some(edge) = self.nodes[pos]
+                .edges
+                .iter_mut()
+                .find(|edge| edge.id == edge_id)
+            {
+                edge.travel_time = travel_time.clone();
+            }
+        }
+        self.route_table.take();
+        Ok(())
+    }
+
+    /// Limits the edge to `tracks` trains at once, the others are held at
+    /// their station until a track is free.
+    pub fn set_edge_tracks(&mut self, name: &str, tracks: u32) -> Result<()> {
+        let edge_id = EdgeId(name.into());
+        if tracks == 0 {
+            return Err(Error::NoTracks(edge_id));
+        }
+        let mut edges = self
+            .nodes
+            .iter_mut()
+            .flat_map(|node| node.edges.iter_mut())
+            .filter(|edge| edge.id == edge_id)
+            .peekable();
+        if edges.peek().is_none() {
+            return Err(Error::UnknownEdge(edge_id));
+        }
+        for edge in edges {
+            edge.tracks = Some(tracks);
+        }
         Ok(())
     }
 
     pub fn add_train(&mut self, name: &str, max_capacity: Kilogram, location: &str) -> Result<()> {
-        let pos = self.find_node_index_by_name(location).ok_or_else(|| {
-            Error::new(
-                ErrorKind::AddTrainError,
-                format!("Node '{location} doesn't exist"),
-            )
-        })?;
+        let pos = self.find_existing_node_index(location)?;
+        let node = &self.nodes[pos];
+        if node.platforms.is_some_and(|platforms| {
+            self.train_handler.count_trains_at_node(&node.id, false) >= platforms as usize
+        }) {
+            return Err(Error::StationFull(node.id.clone()));
+        }
 
         self.train_handler
             .add_train(name, max_capacity, &self.nodes[pos].id)?;
         Ok(())
     }
 
+    /// Train doesn't leave its station before `earliest_departure`, counted
+    /// from the start of the delivery run.
+    pub fn set_train_departure(
+        &mut self,
+        name: &str,
+        earliest_departure: Option<Minute>,
+    ) -> Result<()> {
+        let train_id = TrainId(name.into());
+        let train = self
+            .train_handler
+            .get_train_mut(&train_id)
+            .ok_or(Error::UnknownTrain(train_id))?;
+        train.earliest_departure = earliest_departure;
+        Ok(())
+    }
+
+    /// Makes the train run the scheduled service of `timetable`, from its
+    /// first stop where the train has to be stopped. Each leg needs an edge
+    /// the train can make it along in the time given. The train is
+    /// dispatched on demand again when `None`.
+    pub fn set_train_timetable(&mut self, name: &str, timetable: Option<Timetable>) -> Result<()> {
+        let train_id = TrainId(name.into());
+        let train = self
+            .train_handler
+            .get_train(&train_id)
+            .ok_or_else(|| Error::UnknownTrain(train_id.clone()))?;
+        if let Some(timetable) = &timetable {
+            self.check_timetable(train, timetable)?;
+        }
+        self.train_handler
+            .get_train_mut(&train_id)
+            .unwrap()
+            .timetable = timetable;
+        Ok(())
+    }
+
+    fn check_timetable(&self, train: &Train, timetable: &Timetable) -> Result<()> {
+        for (node_id, _) in &timetable.stops {
+            self.check_scenario_node(node_id)?;
+        }
+        let invalid = || Error::InvalidTimetable(train.id.clone());
+        let (Some((first, start)), Some((last, end))) =
+            (timetable.stops.first(), timetable.stops.last())
+        else {
+            return Err(invalid());
+        };
+        if timetable.stops.len() < 2 || train.get_location().as_ref() != Some(first) {
+            return Err(invalid());
+        }
+        if let Some(period) = &timetable.period {
+            if last != first || period.0 == 0 || end.clone() - start.clone() > *period {
+                return Err(invalid());
+            }
+        }
+
+        for stops in timetable.stops.windows(2) {
+            let ((from, departure), (to, arrival)) = (&stops[0], &stops[1]);
+            let pos = self.find_node_index_by_id(from).unwrap();
+            let has_time = self.nodes[pos].find_edge_with_node(to).is_some_and(|edge| {
+                arrival
+                    .clone()
+                    .checked_sub(departure.clone())
+                    .is_some_and(|time| time >= edge.travel_time)
+            });
+            if !has_time {
+                return Err(Error::TimetableLeg {
+                    train: train.id.clone(),
+                    from: from.clone(),
+                    to: to.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the train, refused while it carries packages unless `force`
+    /// is set, which cancels them too.
+    pub fn remove_train(&mut self, name: &str, force: bool) -> Result<()> {
+        let train_id = TrainId(name.into());
+        if self.train_handler.get_train(&train_id).is_none() {
+            return Err(Error::UnknownTrain(train_id));
+        }
+        let package_ids = self.package_handler.list_packages_in_train(&train_id);
+        if !force && !package_ids.is_empty() {
+            return Err(Error::TrainLoaded(train_id));
+        }
+
+        for package_id in package_ids {
+            self.package_handler.remove_package(&package_id)?;
+        }
+        self.train_handler.remove_train(&train_id)?;
+        Ok(())
+    }
+
+    pub fn set_train_capacity(&mut self, name: &str, max_capacity: Kilogram) -> Result<()> {
+        let train_id = TrainId(name.into());
+        let train = self
+            .train_handler
+            .get_train_mut(&train_id)
+            .ok_or(Error::UnknownTrain(train_id))?;
+        if max_capacity < train.load_size {
+            return Err(Error::OverCapacity(train.id.clone()));
+        }
+        train.max_capacity = max_capacity;
+        Ok(())
+    }
+
+    /// Limits the train to `max_volume` of packages on top of their weight,
+    /// any volume fits when `None`.
+    pub fn set_train_volume(&mut self, name: &str, max_volume: Option<Liter>) -> Result<()> {
+        let train_id = TrainId(name.into());
+        let train = self
+            .train_handler
+            .get_train_mut(&train_id)
+            .ok_or(Error::UnknownTrain(train_id))?;
+        if max_volume
+            .as_ref()
+            .is_some_and(|max_volume| *max_volume < train.load_volume)
+        {
+            return Err(Error::OverCapacity(train.id.clone()));
+        }
+        train.max_volume = max_volume;
+        Ok(())
+    }
+
     pub fn add_package(
         &mut self,
         name: &str,
@@ -111,19 +433,8 @@ impl TrainFreightSystem {
         origin: &str,
         destination: &str,
     ) -> Result<()> {
-        let origin_pos = self.find_node_index_by_name(origin).ok_or_else(|| {
-            Error::new(
-                ErrorKind::AddPackageError,
-                format!("Node '{origin}' doesn't exist"),
-            )
-        })?;
-
-        let destination_pos = self.find_node_index_by_name(destination).ok_or_else(|| {
-            Error::new(
-                ErrorKind::AddPackageError,
-                format!("Node '{destination}' doesn't exist"),
-            )
-        })?;
+        let origin_pos = self.find_existing_node_index(origin)?;
+        let destination_pos = self.find_existing_node_index(destination)?;
 
         let origin_id = self.nodes[origin_pos].id.clone();
         let destination_id = self.nodes[destination_pos].id.clone();
@@ -132,265 +443,754 @@ impl TrainFreightSystem {
         Ok(())
     }
 
-    fn get_travel_time_from_routes(&self, routes: &Vec<NodeId>) -> Minute {
-        let mut travel_time = Minute(0);
-        for i in 1..routes.len() {
-            let pos = self.find_node_index_by_id(&routes[i - 1]).unwrap();
-            if let Some(edge) = self.nodes[pos].find_edge_with_node(&routes[i]) {
-                travel_time = travel_time + edge.travel_time.clone();
-            }
+    /// Cancels the package, taking it off the train it is loaded to.
+    pub fn remove_package(&mut self, name: &str) -> Result<()> {
+        let package = self
+            .package_handler
+            .remove_package(&PackageId(name.into()))?;
+        if let package::Status::LoadedTo(train_id) = &package.status {
+            let train = self.train_handler.get_train_mut(train_id).unwrap();
+            train.take_off(&package);
         }
-
-        travel_time
+        Ok(())
     }
 
-    fn get_all_possible_routes(
-        &self,
-        origin: &NodeId,
-        destination: &NodeId,
-        possible_paths: &mut Vec<Vec<NodeId>>,
-        routes: &mut Vec<NodeId>,
-    ) {
-        if routes.iter().any(|node| node == origin) {
-            return;
+    /// A package loaded to a train has to still fit in it.
+    pub fn set_package_weight(&mut self, name: &str, weight: Kilogram) -> Result<()> {
+        let package_id = PackageId(name.into());
+        let package = self
+            .package_handler
+            .get_package_mut(&package_id)
+            .ok_or(Error::UnknownPackage(package_id))?;
+        if let package::Status::LoadedTo(train_id) = &package.status {
+            let train = self.train_handler.get_train_mut(train_id).unwrap();
+            let load_size = train.load_size.clone() - package.weight.clone() + weight.clone();
+            if load_size > train.max_capacity {
+                return Err(Error::TrainFull {
+                    package: package.id.clone(),
+                    train: train.id.clone(),
+                });
+            }
+            train.load_size = load_size;
         }
+        package.weight = weight;
+        Ok(())
+    }
 
-        routes.push(origin.clone());
-
-        let pos = self.find_node_index_by_id(origin).unwrap();
-        for edge in self.nodes[pos].edges.iter() {
-            if routes.iter().any(|node| node == destination) {
-                possible_paths.push(routes.clone());
-                return;
-            }
-            let mut path: Vec<NodeId> = routes.clone();
-            self.get_all_possible_routes(&edge.node, destination, possible_paths, &mut path);
-            if path.iter().any(|node| node == destination) {
-                possible_paths.push(path);
+    /// Takes room in the trains limited by volume, `None` when it can be
+    /// left out. A package loaded to a train has to still fit in it.
+    pub fn set_package_volume(&mut self, name: &str, volume: Option<Liter>) -> Result<()> {
+        let package_id = PackageId(name.into());
+        let package = self
+            .package_handler
+            .get_package_mut(&package_id)
+            .ok_or(Error::UnknownPackage(package_id))?;
+        if let package::Status::LoadedTo(train_id) = &package.status {
+            let train = self.train_handler.get_train_mut(train_id).unwrap();
+            let load_volume = train.load_volume.clone() - package.get_volume()
+                + volume.clone().unwrap_or_default();
+            if train
+                .max_volume
+                .as_ref()
+                .is_some_and(|max_volume| load_volume > *max_volume)
+            {
+                return Err(Error::TrainFull {
+                    package: package.id.clone(),
+                    train: train.id.clone(),
+                });
             }
+            train.load_volume = load_volume;
         }
+        package.volume = volume;
+        Ok(())
     }
 
-    fn get_least_time_path_to_move_from_point_a_to_point_b(
-        &self,
-        node_a_id: &NodeId,
-        node_b_id: &NodeId,
-    ) -> Vec<NodeId> {
-        let mut possible_routes: Vec<Vec<NodeId>> = vec![];
-        let mut routes: Vec<NodeId> = vec![];
-        self.get_all_possible_routes(
-            node_a_id,
-            node_b_id,
-            &mut possible_routes,
-            &mut routes,
-        );
+    /// Package can't be picked up before `earliest_pickup` and should be
+    /// delivered by `deadline`, both counted from the start of the delivery
+    /// run.
+    pub fn set_package_time_window(
+        &mut self,
+        name: &str,
+        earliest_pickup: Option<Minute>,
+        deadline: Option<Minute>,
+    ) -> Result<()> {
+        let package_id = PackageId(name.into());
+        let package = self
+            .package_handler
+            .get_package_mut(&package_id)
+            .ok_or(Error::UnknownPackage(package_id))?;
+        package.earliest_pickup = earliest_pickup;
+        package.deadline = deadline;
+        Ok(())
+    }
 
-        let mut least_travel_time = Minute(0);
-        let mut least_travel_time_route: Vec<NodeId> = vec![];
-        for path in possible_routes.iter() {
-            let travel_time: Minute = self.get_travel_time_from_routes(path);
-            if least_travel_time == Minute(0) || travel_time < least_travel_time {
-                least_travel_time = travel_time;
-                least_travel_time_route = path.clone();
-            }
+    /// Higher classes are loaded first when a train can't take every package
+    /// at its station.
+    pub fn set_package_priority(&mut self, name: &str, priority: Priority) -> Result<()> {
+        let package_id = PackageId(name.into());
+        let package = self
+            .package_handler
+            .get_package_mut(&package_id)
+            .ok_or(Error::UnknownPackage(package_id))?;
+        package.priority = priority;
+        Ok(())
+    }
+
+    /// Limits the station to `platforms` trains stopped at once, the others
+    /// queue on the edge they arrive on until one leaves.
+    pub fn set_node_platforms(&mut self, name: &str, platforms: u32) -> Result<()> {
+        let pos = self.find_existing_node_index(name)?;
+        let node_id = self.nodes[pos].id.clone();
+        if platforms == 0 {
+            return Err(Error::NoPlatforms(node_id));
         }
+        if self.train_handler.count_trains_at_node(&node_id, false) > platforms as usize {
+            return Err(Error::StationFull(node_id));
+        }
+        self.nodes[pos].platforms = Some(platforms);
+        Ok(())
+    }
+
+    /// Added to the run every time a train loads or unloads at the station.
+    pub fn set_node_handling_time(&mut self, name: &str, handling_time: HandlingTime) -> Result<()> {
+        let pos = self.find_existing_node_index(name)?;
+        self.nodes[pos].handling_time = handling_time;
+        Ok(())
+    }
 
-        least_travel_time_route
+    /// Gives the delivery runs a clock time, every run carries on from where
+    /// the one before stopped. Plans count minutes only when `None`.
+    pub fn set_start(&mut self, start: Option<DateTime>) {
+        self.start = start;
     }
 
-    fn get_least_time_path_to_deliver_package(&self, package: &Package) -> Vec<NodeId> {
-        let origin = package.get_location().unwrap();
-        return self.get_least_time_path_to_move_from_point_a_to_point_b(origin, &package.destination);
+    pub fn get_start(&self) -> Option<&DateTime> {
+        self.start.as_ref()
     }
 
-    fn list_all_undelivered_packages_least_possible_routes(&mut self) -> Vec<Vec<NodeId>> {
-        let packages = self.package_handler.list_undelivered_packages();
-        let mut packages_routes: Vec<Vec<NodeId>> = vec![];
-        for package_id in &packages {
-            let package = self.package_handler.get_package(package_id).unwrap();
-            let routes = self.get_least_time_path_to_deliver_package(package);
-            packages_routes.push(routes);
-        }
-        packages_routes
+    /// Minutes from the start of the next delivery run until `time`, `None`
+    /// without a start or when `time` is before it.
+    pub fn minutes_from_start(&self, time: &DateTime) -> Option<Minute> {
+        time.minutes_since(self.start.as_ref()?)
     }
 
-    fn deliver_packages_in_node(&mut self, node_id: &NodeId, node_index: usize) -> DeliveryResult {
-        let packages = self
-            .package_handler
-            .list_undelivered_packages_at_node(node_id);
-        if packages.is_empty() {
-            return DeliveryResult::NoPackages;
+    pub fn to_scenario(&self) -> Scenario {
+        let mut edges: Vec<EdgeScenario> = vec![];
+        for node in &self.nodes {
+            for edge in &node.edges {
+                // Two-way edges are on both of their nodes
+                let is_listed = edges.iter().any(|listed| {
+                    !listed.directed
+                        && listed.id == edge.id
+                        && listed.node_1 == edge.node
+                        && listed.node_2 == node.id
+                });
+                if is_listed {
+                    continue;
+                }
+                let pos = self.find_node_index_by_id(&edge.node).unwrap();
+                let has_way_back = self.nodes[pos].edges.iter().any(|back| {
+                    back.id == edge.id
+                        && back.node == node.id
+                        && back.travel_time == edge.travel_time
+                });
+                edges.push(EdgeScenario {
+                    id: edge.id.clone(),
+                    node_1: node.id.clone(),
+                    node_2: edge.node.clone(),
+                    travel_time: edge.travel_time.clone(),
+                    tracks: edge.tracks,
+                    directed: !has_way_back,
+                });
+            }
         }
 
-        let biggest_train = if let Some(biggest_train) = self
-            .train_handler
-            .find_largest_capacity_train_in_node(node_id)
-        {
-            biggest_train
-            // load packages going to
-        } else {
-            return DeliveryResult::NoTrains;
-        };
+        Scenario {
+            nodes: self.nodes.iter().map(|node| node.id.clone()).collect(),
+            edges,
+            trains: self.train_handler.trains.clone(),
+            packages: self.package_handler.packages.clone(),
+            handling_times: self
+                .nodes
+                .iter()
+                .filter(|node| node.handling_time != HandlingTime::default())
+                .map(|node| HandlingTimeScenario {
+                    node: node.id.clone(),
+                    handling_time: node.handling_time.clone(),
+                })
+                .collect(),
+            platforms: self
+                .nodes
+                .iter()
+                .filter_map(|node| {
+                    Some(PlatformsScenario {
+                        node: node.id.clone(),
+                        platforms: node.platforms?,
+                    })
+                })
+                .collect(),
+            start: self.start.clone(),
+        }
+    }
 
-        let mut highest_routes: Vec<NodeId> = vec![];
-        for package_id in packages.iter() {
-            let package = self.package_handler.get_package(package_id).unwrap();
-            let routes = self.get_least_time_path_to_deliver_package(package);
-            if routes.len() > highest_routes.len() {
-                highest_routes = routes;
+    pub fn from_scenario(scenario: Scenario) -> Result<Self> {
+        let mut system = Self::default();
+        system.set_start(scenario.start);
+        for node_id in &scenario.nodes {
+            system.add_node(&node_id.0)?;
+        }
+        for edge in &scenario.edges {
+            if edge.directed {
+                system.add_directed_edge(
+                    &edge.id.0,
+                    &edge.node_1.0,
+                    &edge.node_2.0,
+                    edge.travel_time.clone(),
+                )?;
+            } else {
+                system.add_edge(
+                    &edge.id.0,
+                    &edge.node_1.0,
+                    &edge.node_2.0,
+                    edge.travel_time.clone(),
+                )?;
             }
+            if let Some(tracks) = edge.tracks {
+                system.set_edge_tracks(&edge.id.0, tracks)?;
+            }
+        }
+        for handling_time in scenario.handling_times {
+            system.set_node_handling_time(
+                &handling_time.node.0,
+                handling_time.handling_time,
+            )?;
+        }
+        for platforms in &scenario.platforms {
+            system.set_node_platforms(&platforms.node.0, platforms.platforms)?;
         }
 
-        let destination = &highest_routes[1];
-        let filtered_packages = self.get_packages_passing_to_node(destination, &packages);
-        // Before loading packages, check if there are packages closer to
-        // this route that can still fit in this train.
-        // if there are then move train to that direction
-        let all_routes = self.list_all_undelivered_packages_least_possible_routes();
-        for routes in &all_routes {
-            let diff: Vec<&NodeId> = routes
+        for train in scenario.trains {
+            for node_id in train.list_stations() {
+                system.check_scenario_node(node_id)?;
+            }
+            if let (Some(edge_id), [origin, destination]) =
+                (train.get_edge(), &train.list_stations()[..])
+            {
+                let pos = system.find_node_index_by_id(origin).unwrap();
+                let edge = system.nodes[pos].find_edge_by_id(edge_id);
+                if edge.is_none_or(|edge| edge.node != **destination) {
+                    return Err(Error::NoEdge {
+                        from: (*origin).clone(),
+                        edge: edge_id.clone(),
+                    });
+                }
+            }
+            for (node_id, _) in train
+                .timetable
                 .iter()
-                .filter(|node_id| !highest_routes.contains(node_id))
-                .rev()
-                .collect();
-            for check_node_id in diff.clone() {
-                let time1 =
-                    self.get_travel_time_from_routes(&vec![check_node_id.clone(), node_id.clone()]);
-                let time2 = self.get_travel_time_from_routes(&highest_routes);
-                if time1 < time2 {
-                    let packages = self
-                        .package_handler
-                        .list_undelivered_packages_at_node(check_node_id);
-                    for package_id in &packages {
-                        let train = self.train_handler.get_train(&biggest_train).unwrap();
-                        let this_package = self.package_handler.get_package(package_id).unwrap();
-                        if train.can_accomodate_package(this_package) {
-                            let time = self.get_travel_time_from_routes(&vec![
-                                diff[0].clone(),
-                                node_id.clone(),
-                            ]);
-                            self.train_handler
-                                .move_to_node(&biggest_train, node_id, diff[0], time);
-                            return DeliveryResult::TrainPicking;
-                        }
+                .flat_map(|timetable| &timetable.stops)
+            {
+                system.check_scenario_node(node_id)?;
+            }
+            if system.train_handler.get_train(&train.id).is_some() {
+                return Err(Error::DuplicateTrain(train.id));
+            }
+            system.train_handler.trains.push(train);
+        }
+
+        for package in scenario.packages {
+            let mut locations = vec![&package.origin, &package.destination];
+            match &package.status {
+                package::Status::DroppedAt(node_id, _)
+                | package::Status::CantBeTransported(node_id)
+                | package::Status::Unreachable(node_id, _)
+                | package::Status::NotReady(node_id) => locations.push(node_id),
+                package::Status::LoadedTo(train_id) => {
+                    if system.train_handler.get_train(train_id).is_none() {
+                        return Err(Error::UnknownTrain(train_id.clone()));
                     }
                 }
+                package::Status::Delivered(_) | package::Status::Completed => {}
+            }
+            for node_id in locations {
+                system.check_scenario_node(node_id)?;
+            }
+            if system.package_handler.get_package(&package.id).is_some() {
+                return Err(Error::DuplicatePackage(package.id));
             }
+            system.package_handler.packages.push(package);
         }
 
-        for package_id in filtered_packages.iter() {
-            let package = self.package_handler.get_package_mut(package_id).unwrap();
-            self.train_handler.load_package(&biggest_train, package);
+        Ok(system)
+    }
+
+    fn check_scenario_node(&self, node_id: &NodeId) -> Result<()> {
+        if self.find_node_index_by_id(node_id).is_none() {
+            return Err(Error::UnknownNode(node_id.clone()));
         }
+        Ok(())
+    }
 
-        let travel_time = self.nodes[node_index]
-            .find_edge_with_node(destination)
-            .unwrap()
-            .travel_time
-            .clone();
+    pub fn load_scenario(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_scenario(Scenario::read(path)?)
+    }
 
-        self.train_handler
-            .move_to_node(&biggest_train, node_id, destination, travel_time);
+    pub fn save_scenario(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.to_scenario().write(path)
+    }
 
-        if !self
-            .package_handler
-            .list_undelivered_packages_at_node(node_id)
-            .is_empty()
-        {
-            return DeliveryResult::NotAllPackageLoaded;
-        }
+    /// Graphviz rendering of the network, see `dot::render`.
+    pub fn to_dot(&self, positions: bool) -> String {
+        dot::render(self, positions)
+    }
 
-        DeliveryResult::AllPackageLoaded
+    pub fn save_dot(&self, path: impl AsRef<Path>, positions: bool) -> Result<()> {
+        dot::write(self, positions, path)
     }
 
-    fn get_packages_passing_to_node(
-        &self,
-        node_id: &NodeId,
-        packages: &Vec<PackageId>,
-    ) -> Vec<PackageId> {
-        let mut filtered_packages: Vec<PackageId> = vec![];
-        for package_id in packages {
-            let package = self.package_handler.get_package(package_id).unwrap();
-            if self
-                .get_least_time_path_to_move_from_point_a_to_point_b(node_id, &package.destination)
-                .iter()
-                .any(|id| id == node_id)
-            {
-                filtered_packages.push(package_id.clone());
+    fn get_travel_time_from_routes(&self, routes: &[NodeId]) -> Minute {
+        let mut travel_time = Minute(0);
+        for i in 1..routes.len() {
+            let pos = self.find_node_index_by_id(&routes[i - 1]).unwrap();
+            if let Some(edge) = self.nodes[pos].find_edge_with_node(&routes[i]) {
+                travel_time = travel_time + edge.travel_time.clone();
             }
         }
-        filtered_packages
+
+        travel_time
+    }
+
+    pub fn route_table(&self) -> &RouteTable {
+        self.route_table.get_or_init(|| RouteTable::new(&self.nodes))
     }
 
-    fn deliver_packages_in_nodes(&mut self) {
-        let nodes: Vec<NodeId> = self.nodes.iter().map(|node| node.id.clone()).collect();
-        for (index, node_id) in nodes.iter().enumerate() {
-            loop {
-                let result = self.deliver_packages_in_node(node_id, index);
-                match result {
-                    DeliveryResult::NoPackages
-                    | DeliveryResult::NoTrains
-                    | DeliveryResult::AllPackageLoaded
-                    | DeliveryResult::TrainPicking => break,
-                    DeliveryResult::NotAllPackageLoaded => continue,
+    fn find_least_time_route(&self, node_a_id: &NodeId, node_b_id: &NodeId) -> Option<Route> {
+        self.route_table().route(node_a_id, node_b_id)
+    }
+
+    /// Fastest route between the two stations, `None` when the destination
+    /// can't be reached.
+    pub fn find_route(&self, origin: &str, destination: &str) -> Result<Option<Route>> {
+        let origin_pos = self.find_existing_node_index(origin)?;
+        let destination_pos = self.find_existing_node_index(destination)?;
+        Ok(self.find_least_time_route(&self.nodes[origin_pos].id, &self.nodes[destination_pos].id))
+    }
+
+    fn get_least_time_route_to_deliver_package(&self, package: &Package) -> Option<Route> {
+        let origin = package.get_location()?;
+        self.find_least_time_route(origin, &package.destination)
+    }
+
+    // Actions are checked against the state left by the ones before them, so
+    // their events happen right away.
+    fn apply_train_action(
+        &mut self,
+        simulation: &mut Simulation,
+        action: &TrainAction,
+    ) -> Result<()> {
+        match action {
+            TrainAction::Load(train_id, package_id) => {
+                let train = self
+                    .train_handler
+                    .get_train(train_id)
+                    .ok_or_else(|| Error::UnknownTrain(train_id.clone()))?;
+                if train.is_scheduled() {
+                    return Err(Error::TrainScheduled(train_id.clone()));
                 }
+                let package = self
+                    .package_handler
+                    .get_package(package_id)
+                    .ok_or_else(|| Error::UnknownPackage(package_id.clone()))?;
+                let location = train.get_location();
+                if location.is_none() || package.get_location() != location.as_ref() {
+                    return Err(Error::PackageNotAtStation {
+                        package: package_id.clone(),
+                        train: train_id.clone(),
+                    });
+                }
+                if !train.can_accomodate_package(package) {
+                    return Err(Error::TrainFull {
+                        package: package_id.clone(),
+                        train: train_id.clone(),
+                    });
+                }
+
+                simulation.schedule_now(Event::Load(train_id.clone(), package_id.clone()));
+            }
+            TrainAction::Move(train_id, edge_id) => {
+                let train = self
+                    .train_handler
+                    .get_train(train_id)
+                    .ok_or_else(|| Error::UnknownTrain(train_id.clone()))?;
+                if train.is_scheduled() {
+                    return Err(Error::TrainScheduled(train_id.clone()));
+                }
+                let origin = train
+                    .get_location()
+                    .ok_or_else(|| Error::TrainNotStopped(train_id.clone()))?;
+                let pos = self.find_node_index_by_id(&origin).unwrap();
+                if self.nodes[pos].find_edge_by_id(edge_id).is_none() {
+                    return Err(Error::NoEdge {
+                        from: origin,
+                        edge: edge_id.clone(),
+                    });
+                }
+                simulation.schedule_now(Event::Departure(
+                    train_id.clone(),
+                    origin,
+                    edge_id.clone(),
+                ));
             }
+            TrainAction::Wait(_) => {}
+        }
+        self.handle_due_events(simulation);
+        Ok(())
+    }
+
+    fn handle_due_events(&mut self, simulation: &mut Simulation) {
+        while let Some(event) = simulation.next_due_event() {
+            self.handle_event(simulation, event);
         }
-        // have packages not picked up and have trains not moving
-        let dropped_packages = self.package_handler.list_undelivered_packages();
-        for package_id in &dropped_packages {
-            let package = self.package_handler.get_package(package_id).unwrap();
-            let routes = self.get_least_time_path_to_deliver_package(package);
-
-            let mut has_trains_moved = false;
-            for i in 1..routes.len() {
-                for train_id in &self.train_handler.list_stopped_trains_at_node(&routes[i]) {
-                    let this_route = vec![routes[i - 1].clone(), routes[i].clone()];
-                    let travel_time = self.get_travel_time_from_routes(&this_route);
-                    let train = self.train_handler.get_train(train_id).unwrap();
-                    if train.can_accomodate_package(package) {
-                        self.train_handler.move_to_node(
-                            train_id,
-                            &routes[i],
-                            &routes[i - 1],
-                            travel_time,
-                        );
-                        has_trains_moved = true;
-                        break;
+    }
+
+    fn handle_event(&mut self, simulation: &mut Simulation, event: Event) {
+        match event {
+            // The train leaves once loaded and counts as arrived once
+            // unloaded, every package on board is loaded at the origin and
+            // unloaded at the destination.
+            Event::Departure(train_id, origin, edge_id) => {
+                let is_scheduled = self
+                    .train_handler
+                    .get_train(&train_id)
+                    .unwrap()
+                    .is_scheduled();
+                if is_scheduled {
+                    self.board_service(&train_id, &origin);
+                }
+                let pos = self.find_node_index_by_id(&origin).unwrap();
+                let edge = self.nodes[pos].find_edge_by_id(&edge_id).unwrap();
+                let destination = edge.node.clone();
+                let travel_time = edge.travel_time.clone();
+                // Scheduled trains are loaded while they wait for their time
+                let mut loaded = if is_scheduled {
+                    simulation.now()
+                } else {
+                    simulation.now() + self.get_dwell_time(&origin, &train_id)
+                };
+                let train = self.train_handler.get_train(&train_id).unwrap();
+                if let Some(earliest_departure) = train
+                    .earliest_departure
+                    .clone()
+                    .filter(|earliest_departure| *earliest_departure > loaded)
+                {
+                    simulation.plan.record_wait(TrainWait {
+                        train: train_id.clone(),
+                        station: origin.clone(),
+                        edge: edge.id.clone(),
+                        from: loaded.clone(),
+                        until: earliest_departure.clone(),
+                        reason: WaitReason::NotDeparted,
+                    });
+                    loaded = earliest_departure;
+                }
+                let departure = simulation.reserve_track(
+                    &edge.id,
+                    (origin.clone(), destination.clone()),
+                    edge.tracks,
+                    loaded.clone(),
+                    travel_time.clone(),
+                );
+                if departure > loaded {
+                    simulation.plan.record_wait(TrainWait {
+                        train: train_id.clone(),
+                        station: origin.clone(),
+                        edge: edge.id.clone(),
+                        from: loaded,
+                        until: departure.clone(),
+                        reason: WaitReason::TrackTaken,
+                    });
+                }
+                simulation.leave_platform(origin.clone(), departure.clone());
+                let arrival = departure.clone()
+                    + travel_time
+                    + self.get_dwell_time(&destination, &train_id);
+                self.train_handler.move_to_node(
+                    &train_id,
+                    &origin,
+                    &destination,
+                    &edge_id,
+                    arrival.clone(),
+                );
+                if is_scheduled {
+                    let train = self.train_handler.get_train_mut(&train_id).unwrap();
+                    if let Some(timetable) = &mut train.timetable {
+                        timetable.depart();
                     }
                 }
+                simulation.plan.record_departure(TrainMove {
+                    train: train_id.clone(),
+                    departure,
+                    arrival: arrival.clone(),
+                    origin,
+                    destination: destination.clone(),
+                    edge: edge_id,
+                    picked_up: self.package_handler.list_packages_in_train(&train_id),
+                    dropped_off: vec![],
+                });
+                simulation.schedule(arrival, Event::Arrival(train_id, destination));
+            }
+            Event::Arrival(train_id, node_id) => self.arrive(simulation, &train_id, &node_id),
+            Event::Load(train_id, package_id) => {
+                let package = self.package_handler.get_package_mut(&package_id).unwrap();
+                self.train_handler.load_package(&train_id, package);
+            }
+            Event::Unload(train_id, package_id) => {
+                let package = self.package_handler.get_package_mut(&package_id).unwrap();
+                self.train_handler.unload_package(&train_id, package);
+                if matches!(package.status, package::Status::Delivered(_)) {
+                    let now = simulation.now();
+                    simulation.plan.record_delivery(package, &train_id, now);
+                }
+            }
+            Event::PackageReady(package_id) => {
+                let package = self.package_handler.get_package_mut(&package_id).unwrap();
+                package.set_to_ready();
+            }
+            Event::PlatformFreed(node_id) => {
+                simulation.free_platform(&node_id);
+                if !self.has_free_platform(simulation, &node_id) {
+                    return;
+                }
+                let Some((train_id, edge_id, since)) = simulation.take_queued_train(&node_id)
+                else {
+                    return;
+                };
+                // Let in at once when a train left as it arrived
+                if since < simulation.now() {
+                    simulation.plan.record_wait(TrainWait {
+                        train: train_id.clone(),
+                        station: node_id.clone(),
+                        edge: edge_id,
+                        from: since,
+                        until: simulation.now(),
+                        reason: WaitReason::StationFull,
+                    });
+                }
+                self.arrive(simulation, &train_id, &node_id);
             }
+        }
+    }
 
-            // find trains not the path
-            if !has_trains_moved {
-                let train_ids = self.train_handler.list_stopped_trains().clone();
-                for train_id in &train_ids {
-                    let train = self.train_handler.get_train(&train_id).unwrap();
-                    let train_location = train.get_location().unwrap();
-                    let routes = self.get_least_time_path_to_move_from_point_a_to_point_b(&train_location, &package.destination);
-                    let time = self.get_travel_time_from_routes(&vec![train_location.clone(), routes[1].clone()]);
-                    if train.can_accomodate_package(package) {
-                        self.train_handler.move_to_node(&train_id, &train_location, &routes[1], time);
-                        break;
-                    }
+    fn has_free_platform(&self, simulation: &Simulation, node_id: &NodeId) -> bool {
+        let pos = self.find_node_index_by_id(node_id).unwrap();
+        self.nodes[pos].platforms.is_none_or(|platforms| {
+            let taken = self.train_handler.count_trains_at_node(node_id, false)
+                + simulation.count_leaving(node_id);
+            taken < platforms as usize
+        })
+    }
+
+    // Stops the train at the station, or queues it on the edge it came on
+    // when every platform is taken
+    fn arrive(&mut self, simulation: &mut Simulation, train_id: &TrainId, node_id: &NodeId) {
+        if !self.has_free_platform(simulation, node_id) {
+            let train = self.train_handler.get_train(train_id).unwrap();
+            let (train::Status::DeliveringTo(origin, _, edge_id, _)
+            | train::Status::QueuedFor(origin, _, edge_id)) = &train.status
+            else {
+                return;
+            };
+            let (origin, edge_id) = (origin.clone(), edge_id.clone());
+            self.train_handler
+                .queue_for(train_id, &origin, node_id, &edge_id);
+            simulation.queue_train(train_id.clone(), edge_id, node_id.clone());
+            return;
+        }
+
+        self.train_handler.stopped(train_id, node_id);
+        simulation.plan.record_arrival(
+            train_id,
+            self.package_handler.list_packages_in_train(train_id),
+        );
+        self.unload_train(simulation, train_id);
+        self.schedule_service(simulation, train_id);
+    }
+
+    // Sends a scheduled train stopped where its next leg starts off at the
+    // time of its timetable, or right away when it runs late
+    fn schedule_service(&self, simulation: &mut Simulation, train_id: &TrainId) {
+        let train = self.train_handler.get_train(train_id).unwrap();
+        let Some(leg) = train.timetable.as_ref().and_then(Timetable::next_leg) else {
+            return;
+        };
+        if train.get_location().as_ref() != Some(&leg.origin) {
+            return;
+        }
+        let pos = self.find_node_index_by_id(&leg.origin).unwrap();
+        // Removed since the timetable was given, the train stays put
+        let Some(edge) = self.nodes[pos].find_edge_with_node(&leg.destination) else {
+            return;
+        };
+        simulation.schedule(
+            leg.departure.max(simulation.now()),
+            Event::Departure(train_id.clone(), leg.origin, edge.id.clone()),
+        );
+    }
+
+    // Packages booked on the scheduled train and waiting at its station get
+    // on while it has room for them
+    fn board_service(&mut self, train_id: &TrainId, node_id: &NodeId) {
+        for (package_id, _) in self
+            .bookings
+            .iter()
+            .filter(|(_, booked)| booked == train_id)
+        {
+            let package = self.package_handler.get_package_mut(package_id).unwrap();
+            if package.get_location() == Some(node_id) {
+                self.train_handler.load_package(train_id, package);
+            }
+        }
+    }
+
+    fn is_booked(&self, package_id: &PackageId) -> bool {
+        self.bookings.iter().any(|(booked, _)| booked == package_id)
+    }
+
+    /// Books the packages waiting at a station on the scheduled train that
+    /// gets them to their destination the earliest, higher priority classes
+    /// first, as long as the train has room for them on every leg until
+    /// then. The others are left to the trains dispatched on demand.
+    fn book_services(&mut self) {
+        let mut services: Vec<Service> = vec![];
+        for train in &self.train_handler.trains {
+            let Some(timetable) = train.timetable.as_ref().filter(|_| train.is_scheduled()) else {
+                continue;
+            };
+            let legs = timetable.list_upcoming_legs();
+            let mut loads = vec![(Kilogram(0), Liter(0)); legs.len()];
+            // On board until their destination, or on every leg when it
+            // isn't on one of them
+            for package_id in self.package_handler.list_packages_in_train(&train.id) {
+                let package = self.package_handler.get_package(&package_id).unwrap();
+                let last = legs
+                    .iter()
+                    .position(|leg| leg.destination == package.destination)
+                    .unwrap_or(legs.len().saturating_sub(1));
+                for load in loads.iter_mut().take(last + 1) {
+                    load.0 = load.0.clone() + package.weight.clone();
+                    load.1 = load.1.clone() + package.get_volume();
+                }
+            }
+            services.push((train, legs, loads));
+        }
+
+        let mut packages: Vec<&Package> = self
+            .package_handler
+            .packages
+            .iter()
+            .filter(|package| {
+                matches!(
+                    package.status,
+                    package::Status::DroppedAt(_, _) | package::Status::NotReady(_)
+                )
+            })
+            .collect();
+        packages.sort_by_key(|package| package.priority);
+
+        let mut bookings = vec![];
+        for package in packages {
+            let station = package.get_station().unwrap();
+            let ready_time = package.get_ready_time().cloned().unwrap_or_default();
+            // Service with the first and last leg the package is on
+            let mut booking: Option<(usize, usize, usize)> = None;
+            for (service, (train, legs, loads)) in services.iter().enumerate() {
+                let Some(first) = legs
+                    .iter()
+                    .position(|leg| leg.origin == *station && leg.departure >= ready_time)
+                else {
+                    continue;
+                };
+                let Some(last) = legs[first..]
+                    .iter()
+                    .position(|leg| leg.destination == package.destination)
+                    .map(|last| first + last)
+                else {
+                    continue;
+                };
+                let has_room = loads[first..=last].iter().all(|(weight, volume)| {
+                    weight.clone() + package.weight.clone() <= train.max_capacity
+                        && train.max_volume.as_ref().is_none_or(|max_volume| {
+                            volume.clone() + package.get_volume() <= *max_volume
+                        })
+                });
+                let is_earlier = booking.is_none_or(|(booked, _, booked_last)| {
+                    legs[last].arrival < services[booked].1[booked_last].arrival
+                });
+                if has_room && is_earlier {
+                    booking = Some((service, first, last));
                 }
             }
 
-            // find trains that 
+            let Some((service, first, last)) = booking else {
+                continue;
+            };
+            let (train, _, loads) = &mut services[service];
+            for load in &mut loads[first..=last] {
+                load.0 = load.0.clone() + package.weight.clone();
+                load.1 = load.1.clone() + package.get_volume();
+            }
+            bookings.push((package.id.clone(), train.id.clone()));
         }
+        self.bookings = bookings;
     }
 
-    fn train_arrived(&mut self) -> Minute {
-        let least_travel_time = self
-            .train_handler
-            .get_moving_train_lowest_travel_time()
-            .unwrap_or(Minute(0));
-        self.train_handler.time_elapsed(&least_travel_time);
+    // Only the scheduled trains have anything left to do, and none of them
+    // carries or is booked for a package still to deliver
+    fn is_left_to_services(&self, simulation: &Simulation) -> bool {
+        let is_scheduled = |train_id: &TrainId| {
+            self.train_handler
+                .get_train(train_id)
+                .is_some_and(Train::is_scheduled)
+        };
+        let has_service_packages = self.package_handler.packages.iter().any(|package| {
+            !package.is_delivered()
+                && (self.is_booked(&package.id)
+                    || matches!(&package.status, package::Status::LoadedTo(train_id)
+                        if is_scheduled(train_id)))
+        });
+        !has_service_packages
+            && !simulation.has_queued_trains()
+            && simulation.list_events().all(|event| match event {
+                Event::Departure(train_id, _, _) | Event::Arrival(train_id, _) => {
+                    is_scheduled(train_id)
+                }
+                Event::PlatformFreed(_) => true,
+                _ => false,
+            })
+    }
 
-        self.train_handler
-            .unload_packages_in_trains_that_stopped(&mut self.package_handler.packages);
+    fn get_dwell_time(&self, node_id: &NodeId, train_id: &TrainId) -> Minute {
+        let packages = self.package_handler.list_packages_in_train(train_id);
+        let weight: Kilogram = packages
+            .iter()
+            .map(|package_id| &self.package_handler.get_package(package_id).unwrap().weight)
+            .sum();
+        let pos = self.find_node_index_by_id(node_id).unwrap();
+        self.nodes[pos]
+            .handling_time
+            .get_dwell_time(packages.len(), &weight)
+    }
 
-        least_travel_time.clone()
+    // Scheduled trains keep the packages going further on board
+    fn unload_train(&self, simulation: &mut Simulation, train_id: &TrainId) {
+        let train = self.train_handler.get_train(train_id).unwrap();
+        for package_id in self.package_handler.list_packages_in_train(train_id) {
+            let package = self.package_handler.get_package(&package_id).unwrap();
+            if train.is_scheduled() && train.get_location().as_ref() != Some(&package.destination) {
+                continue;
+            }
+            simulation.schedule_now(Event::Unload(train_id.clone(), package_id));
+        }
     }
 
     pub fn blacklist_packages_that_cant_be_transported(&mut self) {
@@ -410,43 +1210,126 @@ impl TrainFreightSystem {
         }
     }
 
-    pub fn deliver_packages(&mut self) -> Minute {
-        let mut total_delivery_time = Minute(0);
+    /// Marks the packages whose destination can't be reached from their
+    /// station, or that no train able to carry them can get to, so that planning goes
+    /// on with the others. Packages marked before are checked again as the
+    /// network or the trains may have changed.
+    pub fn mark_unreachable_packages(&mut self) {
+        for package in self.package_handler.list_unreachable_packages_mut() {
+            package.drop_to_origin();
+        }
 
-        self.blacklist_packages_that_cant_be_transported();
+        let route_table = self.route_table();
+        let mut unreachable_packages = vec![];
+        for package_id in self.package_handler.list_undelivered_packages() {
+            let package = self.package_handler.get_package(&package_id).unwrap();
+            let origin = package.get_location().unwrap();
+            if !route_table.can_reach(origin, &package.destination) {
+                unreachable_packages.push((package_id, UnreachableReason::NoRoute));
+                continue;
+            }
 
-        while self.package_handler.have_undelivered_packages() {
-            self.deliver_packages_in_nodes();
+            let has_train = self.train_handler.trains.iter().any(|train| {
+                if !train.can_accomodate_package(package) {
+                    return false;
+                }
+                // A scheduled train only takes it along its own line
+                if let Some(timetable) = train.timetable.as_ref().filter(|_| train.is_scheduled()) {
+                    return timetable.can_carry(origin, &package.destination);
+                }
+                let location = match &train.status {
+                    train::Status::StoppedAt(node_id)
+                    | train::Status::DeliveringTo(_, node_id, _, _)
+                    | train::Status::QueuedFor(_, node_id, _) => node_id,
+                    train::Status::NotAvailable => return false,
+                };
+                route_table.can_reach(location, origin)
+            });
+            if !has_train {
+                unreachable_packages.push((package_id, UnreachableReason::NoTrain));
+            }
+        }
 
-            let travel_time = self.train_arrived();
+        for (package_id, reason) in unreachable_packages {
+            let package = self.package_handler.get_package_mut(&package_id).unwrap();
+            package.set_to_unreachable(reason);
+        }
+    }
 
-            for train in &self.train_handler.trains {
-                let (origin, destination) = match &train.status {
-                    train::Status::StoppedAt(location) => (location.0.to_owned(), "".to_string()),
-                    train::Status::DeliveringTo(origin, destination, _) => {
-                        (origin.0.to_owned(), destination.0.to_owned())
-                    }
-                    _ => ("".into(), "".into()),
-                };
+    pub fn deliver_packages(
+        &mut self,
+        strategy: &mut dyn DispatchStrategy,
+    ) -> Result<DeliveryPlan> {
+        let mut simulation = Simulation::default();
+        simulation.plan.start = self.start.clone();
 
-                println!(
-                    "W={}, T={}, N1={}, P1={:?}, N2={}, P2 ={:?}",
-                    total_delivery_time.0,
-                    train.id.0,
-                    origin,
-                    self.package_handler
-                        .list_package_names_in_transit(&train.id),
-                    destination,
-                    self.package_handler.list_package_names_delivered(&train.id)
+        self.blacklist_packages_that_cant_be_transported();
+        self.mark_unreachable_packages();
+
+        for (train_id, origin, destination, edge_id, arrival) in
+            self.train_handler.list_moving_trains()
+        {
+            // Taken until the train gets off, the start of the run being the
+            // earliest it could be
+            let pos = self.find_node_index_by_id(&origin).unwrap();
+            if let Some(edge) = self.nodes[pos].find_edge_by_id(&edge_id) {
+                let on_edge = edge.travel_time.clone().min(arrival.clone());
+                simulation.reserve_track(
+                    &edge.id,
+                    (origin, destination.clone()),
+                    edge.tracks,
+                    arrival.clone() - on_edge.clone(),
+                    on_edge,
                 );
             }
+            simulation.schedule(arrival, Event::Arrival(train_id, destination));
+        }
+        // Queued at the end of the last run, they try again
+        for (train_id, _, destination) in self.train_handler.list_queued_trains() {
+            simulation.schedule_now(Event::Arrival(train_id, destination));
+        }
+        for package in self.package_handler.packages.iter_mut() {
+            if let Some(earliest_pickup) = package.hold_until_ready() {
+                simulation.schedule(earliest_pickup, Event::PackageReady(package.id.clone()));
+            }
+        }
+        self.book_services();
+        for train_id in self.train_handler.list_scheduled_trains() {
+            self.schedule_service(&mut simulation, &train_id);
+        }
+
+        while self.package_handler.have_undelivered_packages() {
+            for action in strategy.dispatch(self) {
+                self.apply_train_action(&mut simulation, &action)?;
+            }
+
+            // Trains that didn't leave drop what they were loaded with
+            for train_id in self.train_handler.list_stopped_trains() {
+                self.unload_train(&mut simulation, &train_id);
+            }
+            self.handle_due_events(&mut simulation);
+
+            // Nothing left to happen, the remaining packages have no route to
+            // their destination. Scheduled trains run on regardless, they
+            // only count while they carry or wait for one of the packages.
+            if self.is_left_to_services(&simulation) {
+                break;
+            }
+            let Some(time) = simulation.advance() else {
+                break;
+            };
+            self.handle_due_events(&mut simulation);
 
-            total_delivery_time = total_delivery_time + travel_time;
+            simulation.plan.total_time = time;
 
             self.package_handler.delist_delivered_packages();
         }
 
-        total_delivery_time
+        self.bookings.clear();
+        self.train_handler
+            .shift_arrivals(&simulation.plan.total_time);
+        self.start = simulation.plan.clock_time(&simulation.plan.total_time);
+        Ok(simulation.plan)
     }
 }
 
@@ -454,6 +1337,10 @@ impl TrainFreightSystem {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::train_freight_system::{
+        dispatch::{DeadlineDispatch, DefaultDispatch},
+        package::PackageId,
+    };
 
     #[test] 
     fn test_system() {
@@ -465,20 +1352,908 @@ mod tests {
         system.add_edge("E2", "C", "B", Minute(10)).expect("can't add edge");
         system.add_package("K1", Kilogram(5), "A", "C").expect("Can't add package");
         system.add_train("Q1", Kilogram(6), "B").expect("Can't add train");
-        let total_travel_time = system.deliver_packages();
+        let total_travel_time = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages").total_time;
         println!("total travel time {:?}", total_travel_time);
         assert_eq!(total_travel_time, Minute(70));
 
         // Can't transport package
         system.add_package("K2", Kilogram(25), "B", "A").expect("Can't add package");
-        let total_travel_time = system.deliver_packages();
+        let total_travel_time = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages").total_time;
         println!("total travel time {:?}", total_travel_time);
         assert_eq!(total_travel_time, Minute(0));
  
         println!("start");
         system.add_train("Q2", Kilogram(30), "C").expect("Can't add train");
-        let total_travel_time = system.deliver_packages();
+        let total_travel_time = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages").total_time;
         println!("total travel time {:?}", total_travel_time);
         assert_eq!(total_travel_time, Minute(40));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_add_errors() {
+        let mut system = TrainFreightSystem::default();
+        system.add_node("A").expect("can't add node");
+        system.add_node("B").expect("can't add node");
+        system.add_edge("E1", "A", "B", Minute(30)).expect("can't add edge");
+        system.add_train("Q1", Kilogram(6), "B").expect("Can't add train");
+        system.add_package("K1", Kilogram(5), "A", "B").expect("Can't add package");
+
+        assert!(matches!(system.add_node("A"), Err(Error::DuplicateNode(node)) if node.0 == "A"));
+        assert!(matches!(
+            system.add_edge("E2", "A", "C", Minute(10)),
+            Err(Error::UnknownNode(node)) if node.0 == "C"
+        ));
+        assert!(matches!(
+            system.add_edge("E2", "A", "A", Minute(10)),
+            Err(Error::SelfLoop(edge)) if edge.0 == "E2"
+        ));
+        assert!(matches!(
+            system.add_edge("E1", "A", "B", Minute(10)),
+            Err(Error::DuplicateEdge(edge)) if edge.0 == "E1"
+        ));
+        assert!(matches!(
+            system.add_train("Q1", Kilogram(6), "A"),
+            Err(Error::DuplicateTrain(train)) if train.0 == "Q1"
+        ));
+        assert!(matches!(
+            system.add_train("Q2", Kilogram(6), "C"),
+            Err(Error::UnknownNode(node)) if node.0 == "C"
+        ));
+        assert!(matches!(
+            system.add_package("K1", Kilogram(5), "A", "B"),
+            Err(Error::DuplicatePackage(package)) if package.0 == "K1"
+        ));
+        assert!(matches!(
+            system.add_package("K2", Kilogram(5), "A", "C"),
+            Err(Error::UnknownNode(node)) if node.0 == "C"
+        ));
+    }
+
+    #[test]
+    fn test_remove_and_update() {
+        let mut system = TrainFreightSystem::default();
+        for node in ["A", "B", "C"] {
+            system.add_node(node).expect("can't add node");
+        }
+        system.add_edge("E1", "A", "B", Minute(30)).expect("can't add edge");
+        system.add_edge("E2", "B", "C", Minute(10)).expect("can't add edge");
+        system.add_package("K1", Kilogram(5), "A", "C").expect("Can't add package");
+        system.add_package("K2", Kilogram(2), "B", "C").expect("Can't add package");
+        system.add_train("Q1", Kilogram(6), "A").expect("Can't add train");
+
+        assert!(matches!(
+            system.remove_node("A", false),
+            Err(Error::NodeInUse(node)) if node.0 == "A"
+        ));
+        assert!(matches!(
+            system.remove_edge("E9"),
+            Err(Error::UnknownEdge(edge)) if edge.0 == "E9"
+        ));
+
+        system.set_edge_travel_time("E1", None, Minute(20)).expect("can't update edge");
+        assert_eq!(
+            system.route_table().travel_time(&NodeId("C".into()), &NodeId("A".into())),
+            Some(Minute(30))
+        );
+
+        // Loaded trains keep their packages until forced
+        let package = system.package_handler.get_package_mut(&PackageId("K1".into())).unwrap();
+        system.train_handler.load_package(&TrainId("Q1".into()), package);
+        assert!(matches!(
+            system.set_train_capacity("Q1", Kilogram(4)),
+            Err(Error::OverCapacity(train)) if train.0 == "Q1"
+        ));
+        assert!(matches!(
+            system.set_package_weight("K1", Kilogram(7)),
+            Err(Error::TrainFull { package, train }) if package.0 == "K1" && train.0 == "Q1"
+        ));
+        system.set_package_weight("K1", Kilogram(3)).expect("can't update package");
+        assert_eq!(system.train_handler.trains[0].load_size, Kilogram(3));
+        assert!(matches!(
+            system.remove_train("Q1", false),
+            Err(Error::TrainLoaded(train)) if train.0 == "Q1"
+        ));
+        system.remove_package("K1").expect("can't remove package");
+        assert_eq!(system.train_handler.trains[0].load_size, Kilogram(0));
+        system.set_train_capacity("Q1", Kilogram(4)).expect("can't update train");
+
+        // Forced, the train at A goes with it but K2 at B stays
+        system.remove_node("A", true).expect("can't remove node");
+        assert!(system.train_handler.trains.is_empty());
+        assert_eq!(system.package_handler.packages.len(), 1);
+        assert!(system.nodes[0].find_edge_with_node(&NodeId("A".into())).is_none());
+
+        system.remove_edge("E2").expect("can't remove edge");
+        assert!(system.nodes.iter().all(|node| node.edges.is_empty()));
+        assert!(!system
+            .route_table()
+            .is_connected(&NodeId("B".into()), &NodeId("C".into())));
+    }
+
+    #[test]
+    fn test_package_volume() {
+        let mut system = TrainFreightSystem::default();
+        system.add_node("A").expect("can't add node");
+        system.add_node("B").expect("can't add node");
+        system.add_edge("E1", "A", "B", Minute(30)).expect("can't add edge");
+        system.add_package("K1", Kilogram(1), "A", "B").expect("Can't add package");
+        system.add_package("K2", Kilogram(1), "A", "B").expect("Can't add package");
+        system.set_package_volume("K1", Some(Liter(300))).expect("can't update package");
+        system.set_package_volume("K2", Some(Liter(800))).expect("can't update package");
+        system.add_train("Q1", Kilogram(6), "A").expect("Can't add train");
+        system.set_train_volume("Q1", Some(Liter(500))).expect("can't update train");
+
+        // Light enough but too bulky for the only train
+        system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+        assert_eq!(
+            system.package_handler.packages[1].status,
+            package::Status::CantBeTransported(NodeId("A".into()))
+        );
+        assert_eq!(system.package_handler.packages[0].status, package::Status::Completed);
+
+        system.add_package("K3", Kilogram(1), "A", "B").expect("Can't add package");
+        system.set_package_volume("K3", Some(Liter(400))).expect("can't update package");
+        let package = system.package_handler.get_package_mut(&PackageId("K3".into())).unwrap();
+        system.train_handler.load_package(&TrainId("Q1".into()), package);
+        assert_eq!(system.train_handler.trains[0].load_volume, Liter(400));
+        assert!(matches!(
+            system.set_train_volume("Q1", Some(Liter(300))),
+            Err(Error::OverCapacity(train)) if train.0 == "Q1"
+        ));
+        assert!(matches!(
+            system.set_package_volume("K3", Some(Liter(600))),
+            Err(Error::TrainFull { package, train }) if package.0 == "K3" && train.0 == "Q1"
+        ));
+        system.remove_package("K3").expect("can't remove package");
+        assert_eq!(system.train_handler.trains[0].load_volume, Liter(0));
+
+        // A train without a volume limit takes it
+        system.set_train_volume("Q1", None).expect("can't update train");
+        system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+        assert_eq!(system.package_handler.packages[1].status, package::Status::Completed);
+    }
+
+    #[test]
+    fn test_delivery_plan() {
+        let mut system = TrainFreightSystem::default();
+        system.add_node("A").expect("can't add node");
+        system.add_node("B").expect("can't add node");
+        system.add_node("C").expect("can't add node");
+        system.add_edge("E1", "A", "B", Minute(30)).expect("can't add edge");
+        system.add_edge("E2", "C", "B", Minute(10)).expect("can't add edge");
+        system.add_package("K1", Kilogram(5), "A", "C").expect("Can't add package");
+        system.add_train("Q1", Kilogram(6), "B").expect("Can't add train");
+        let plan = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+
+        assert_eq!(plan.total_time, Minute(70));
+        let moves: Vec<(u32, &str, &str)> = plan
+            .moves
+            .iter()
+            .map(|train_move| {
+                (
+                    train_move.departure.0,
+                    train_move.origin.0.as_str(),
+                    train_move.destination.0.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(moves, vec![(0, "B", "A"), (30, "A", "B"), (60, "B", "C")]);
+        let package_id = PackageId("K1".into());
+        assert!(plan.moves[0].picked_up.is_empty());
+        assert_eq!(plan.moves[1].picked_up, vec![package_id.clone()]);
+        assert_eq!(plan.moves[1].dropped_off, vec![package_id.clone()]);
+        assert!(plan.list_packages_delivered_by(&plan.moves[1]).is_empty());
+        assert_eq!(plan.list_packages_delivered_by(&plan.moves[2]), vec![package_id.clone()]);
+        assert_eq!(plan.delivery_time(&package_id), Some(&Minute(70)));
+    }
+
+    #[test]
+    fn test_no_route() {
+        let mut system = TrainFreightSystem::default();
+        system.add_node("A").expect("can't add node");
+        system.add_node("B").expect("can't add node");
+        system.add_node("C").expect("can't add node");
+        system.add_edge("E1", "A", "B", Minute(30)).expect("can't add edge");
+        system.add_package("K1", Kilogram(5), "A", "C").expect("Can't add package");
+        system.add_package("K2", Kilogram(5), "B", "A").expect("Can't add package");
+        system.add_train("Q1", Kilogram(6), "B").expect("Can't add train");
+
+        assert!(system
+            .find_least_time_route(&NodeId("A".into()), &NodeId("C".into()))
+            .is_none());
+        let total_travel_time = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages").total_time;
+        assert_eq!(total_travel_time, Minute(30));
+        let package = system.package_handler.get_package(&PackageId("K1".into())).unwrap();
+        assert_eq!(
+            package.status,
+            package::Status::Unreachable(NodeId("A".into()), UnreachableReason::NoRoute)
+        );
+    }
+
+    #[test]
+    fn test_unreachable_packages() {
+        let mut system = TrainFreightSystem::default();
+        for node in ["A", "B", "C", "D"] {
+            system.add_node(node).expect("can't add node");
+        }
+        system.add_edge("E1", "A", "B", Minute(30)).expect("can't add edge");
+        system.add_edge("E2", "C", "D", Minute(10)).expect("can't add edge");
+        system.add_package("K1", Kilogram(5), "A", "C").expect("Can't add package");
+        system.add_package("K2", Kilogram(5), "C", "D").expect("Can't add package");
+        system.add_package("K3", Kilogram(5), "B", "A").expect("Can't add package");
+        system.add_train("Q1", Kilogram(6), "A").expect("Can't add train");
+
+        let plan = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+        assert_eq!(plan.delivery_time(&PackageId("K3".into())), Some(&Minute(60)));
+        let statuses: Vec<&package::Status> = system
+            .package_handler
+            .packages
+            .iter()
+            .map(|package| &package.status)
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                &package::Status::Unreachable(NodeId("A".into()), UnreachableReason::NoRoute),
+                &package::Status::Unreachable(NodeId("C".into()), UnreachableReason::NoTrain),
+                &package::Status::Completed,
+            ]
+        );
+
+        // Checked again on the next run
+        system.add_train("Q2", Kilogram(6), "D").expect("Can't add train");
+        system.add_edge("E3", "B", "C", Minute(10)).expect("can't add edge");
+        system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+        assert!(system
+            .package_handler
+            .packages
+            .iter()
+            .all(|package| package.status == package::Status::Completed));
+    }
+
+    #[test]
+    fn test_train_departure_and_start() {
+        let mut system = TrainFreightSystem::default();
+        system.add_node("A").expect("can't add node");
+        system.add_node("B").expect("can't add node");
+        system.add_edge("E1", "A", "B", Minute(30)).expect("can't add edge");
+        system.add_package("K1", Kilogram(5), "A", "B").expect("Can't add package");
+        system.add_train("Q1", Kilogram(6), "A").expect("Can't add train");
+        system.set_start(DateTime::new(2024, 5, 1, 8, 0));
+        let departure = "2024-05-01 08:20".parse().unwrap();
+        let departure = system.minutes_from_start(&departure);
+        assert_eq!(departure, Some(Minute(20)));
+        system.set_train_departure("Q1", departure).expect("can't set departure");
+        assert!(matches!(
+            system.set_train_departure("Q2", None),
+            Err(Error::UnknownTrain(train)) if train.0 == "Q2"
+        ));
+
+        let plan = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+        assert_eq!(plan.moves[0].departure, Minute(20));
+        assert_eq!(plan.waits[0].reason, WaitReason::NotDeparted);
+        assert_eq!(plan.waits[0].until, Minute(20));
+        assert_eq!(
+            plan.clock_time(&plan.total_time).map(|end| end.to_string()),
+            Some("2024-05-01 08:50".into())
+        );
+
+        // The next run starts where this one stopped
+        assert_eq!(system.get_start(), DateTime::new(2024, 5, 1, 8, 50).as_ref());
+        assert_eq!(system.train_handler.trains[0].earliest_departure, None);
+        let before = "2024-05-01 08:40".parse().unwrap();
+        assert_eq!(system.minutes_from_start(&before), None);
+    }
+
+    #[test]
+    fn test_package_time_window() {
+        let mut system = TrainFreightSystem::default();
+        system.add_node("A").expect("can't add node");
+        system.add_node("B").expect("can't add node");
+        system.add_edge("E1", "A", "B", Minute(30)).expect("can't add edge");
+        system.add_package("K1", Kilogram(5), "A", "B").expect("Can't add package");
+        system
+            .set_package_time_window("K1", Some(Minute(20)), Some(Minute(40)))
+            .expect("Can't set time window");
+        system.add_train("Q1", Kilogram(6), "A").expect("Can't add train");
+        assert!(matches!(
+            system.set_package_time_window("K2", None, None),
+            Err(Error::UnknownPackage(package)) if package.0 == "K2"
+        ));
+
+        let plan = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+        assert_eq!(plan.total_time, Minute(50));
+        assert_eq!(plan.moves[0].departure, Minute(20));
+        let late: Vec<(&str, &Option<Minute>)> = plan
+            .list_late_deliveries()
+            .into_iter()
+            .map(|delivery| (delivery.package.0.as_str(), &delivery.lateness))
+            .collect();
+        assert_eq!(late, vec![("K1", &Some(Minute(10)))]);
+    }
+
+    #[test]
+    fn test_package_priority() {
+        let mut system = TrainFreightSystem::default();
+        system.add_node("A").expect("can't add node");
+        system.add_node("B").expect("can't add node");
+        system.add_edge("E1", "A", "B", Minute(30)).expect("can't add edge");
+        system.add_package("K1", Kilogram(5), "A", "B").expect("Can't add package");
+        system.add_package("K2", Kilogram(5), "A", "B").expect("Can't add package");
+        system.add_package("K3", Kilogram(4), "A", "B").expect("Can't add package");
+        system.set_package_priority("K1", Priority::Economy).expect("Can't set priority");
+        system.set_package_priority("K2", Priority::Express).expect("Can't set priority");
+        system.add_train("Q1", Kilogram(5), "A").expect("Can't add train");
+        assert!(matches!(
+            system.set_package_priority("K4", Priority::Express),
+            Err(Error::UnknownPackage(package)) if package.0 == "K4"
+        ));
+
+        let plan = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+        let loaded: Vec<&Vec<PackageId>> = plan
+            .moves
+            .iter()
+            .filter(|train_move| !train_move.picked_up.is_empty())
+            .map(|train_move| &train_move.picked_up)
+            .collect();
+        assert_eq!(
+            loaded,
+            vec![
+                &vec![PackageId("K2".into())],
+                &vec![PackageId("K3".into())],
+                &vec![PackageId("K1".into())],
+            ]
+        );
+        let classes: Vec<(Priority, usize, Minute)> = plan
+            .list_delivery_times_by_priority()
+            .into_iter()
+            .map(|class| (class.priority, class.delivered, class.latest_time))
+            .collect();
+        assert_eq!(
+            classes,
+            vec![
+                (Priority::Express, 1, Minute(30)),
+                (Priority::Standard, 1, Minute(90)),
+                (Priority::Economy, 1, Minute(150)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_handling_time() {
+        let mut system = TrainFreightSystem::default();
+        system.add_node("A").expect("can't add node");
+        system.add_node("B").expect("can't add node");
+        system.add_edge("E1", "A", "B", Minute(30)).expect("can't add edge");
+        system.add_package("K1", Kilogram::from_tonnes(5), "A", "B").expect("Can't add package");
+        system.add_train("Q1", Kilogram::from_tonnes(6), "B").expect("Can't add train");
+        let loading = HandlingTime {
+            per_stop: Minute(5),
+            per_package: Minute(2),
+            ..Default::default()
+        };
+        system.set_node_handling_time("A", loading).expect("can't set handling time");
+        let unloading = HandlingTime {
+            per_tonne: Minute(1),
+            ..Default::default()
+        };
+        system.set_node_handling_time("B", unloading).expect("can't set handling time");
+
+        assert!(matches!(
+            system.set_node_handling_time("C", HandlingTime::default()),
+            Err(Error::UnknownNode(node)) if node.0 == "C"
+        ));
+
+        // Empty train going to A doesn't stop for handling
+        let plan = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+        let times: Vec<(u32, u32)> = plan
+            .moves
+            .iter()
+            .map(|train_move| (train_move.departure.0, train_move.arrival.0))
+            .collect();
+        assert_eq!(times, vec![(0, 30), (37, 72)]);
+        assert_eq!(plan.delivery_time(&PackageId("K1".into())), Some(&Minute(72)));
+        assert_eq!(plan.total_time, Minute(72));
+
+        // Half a tonne at 3 minutes a tonne takes 2 minutes, rounded up
+        let handling_time = HandlingTime {
+            per_tonne: Minute(3),
+            ..Default::default()
+        };
+        assert_eq!(handling_time.get_dwell_time(1, &Kilogram(500)), Minute(2));
+
+        // The heaviest loads take as long as a time can be instead of
+        // overflowing
+        let unloading = HandlingTime {
+            per_tonne: Minute(2000),
+            ..Default::default()
+        };
+        system.set_node_handling_time("B", unloading).expect("can't set handling time");
+        system.add_train("Q2", Kilogram(3_000_000_000), "A").expect("Can't add train");
+        system.add_package("K2", Kilogram(3_000_000_000), "A", "B").expect("Can't add package");
+        let plan = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+        assert_eq!(plan.total_time, Minute(u32::MAX));
+    }
+
+    #[test]
+    fn test_single_track() {
+        let mut system = TrainFreightSystem::default();
+        system.add_node("A").expect("can't add node");
+        system.add_node("B").expect("can't add node");
+        system.add_edge("E1", "A", "B", Minute(30)).expect("can't add edge");
+        system.set_edge_tracks("E1", 1).expect("can't set tracks");
+        system.add_package("K1", Kilogram(5), "A", "B").expect("Can't add package");
+        system.add_package("K2", Kilogram(5), "B", "A").expect("Can't add package");
+        system.add_train("Q1", Kilogram(5), "A").expect("Can't add train");
+        system.add_train("Q2", Kilogram(5), "B").expect("Can't add train");
+        let mut two_tracks = system.clone();
+        two_tracks.set_edge_tracks("E1", 2).expect("can't set tracks");
+
+        assert!(matches!(
+            system.set_edge_tracks("E2", 1),
+            Err(Error::UnknownEdge(edge)) if edge.0 == "E2"
+        ));
+        assert!(matches!(
+            system.set_edge_tracks("E1", 0),
+            Err(Error::NoTracks(edge)) if edge.0 == "E1"
+        ));
+
+        let plan = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+        assert_eq!(plan.total_time, Minute(60));
+        assert_eq!(plan.moves[1].departure, Minute(30));
+        assert_eq!(
+            plan.waits,
+            vec![TrainWait {
+                train: TrainId("Q2".into()),
+                station: NodeId("B".into()),
+                edge: EdgeId("E1".into()),
+                from: Minute(0),
+                until: Minute(30),
+                reason: WaitReason::TrackTaken,
+            }]
+        );
+
+        let plan = two_tracks
+            .deliver_packages(&mut DefaultDispatch)
+            .expect("can't deliver packages");
+        assert_eq!(plan.total_time, Minute(30));
+        assert!(plan.waits.is_empty());
+    }
+
+    #[test]
+    fn test_deadline_dispatch() {
+        let mut system = TrainFreightSystem::default();
+        for node in ["A", "B", "C", "D"] {
+            system.add_node(node).expect("can't add node");
+        }
+        system.add_edge("E1", "A", "B", Minute(30)).expect("can't add edge");
+        system.add_edge("E2", "B", "C", Minute(10)).expect("can't add edge");
+        system.add_edge("E3", "A", "D", Minute(10)).expect("can't add edge");
+        system.add_package("K1", Kilogram(5), "B", "D").expect("Can't add package");
+        system.add_package("K2", Kilogram(5), "B", "C").expect("Can't add package");
+        system
+            .set_package_time_window("K2", None, Some(Minute(10)))
+            .expect("Can't set time window");
+        system.add_train("Q1", Kilogram(5), "B").expect("Can't add train");
+
+        let k2 = PackageId("K2".into());
+        let plan = system
+            .clone()
+            .deliver_packages(&mut DefaultDispatch)
+            .expect("can't deliver packages");
+        assert_eq!(plan.delivery_time(&k2), Some(&Minute(90)));
+        assert_eq!(plan.list_late_deliveries().len(), 1);
+
+        let plan = system
+            .deliver_packages(&mut DeadlineDispatch)
+            .expect("can't deliver packages");
+        assert_eq!(plan.delivery_time(&k2), Some(&Minute(10)));
+        assert!(plan.list_late_deliveries().is_empty());
+        assert_eq!(plan.total_time, Minute(60));
+    }
+
+    #[test]
+    fn test_route_table_invalidated_on_network_change() {
+        let mut system = TrainFreightSystem::default();
+        system.add_node("A").expect("can't add node");
+        system.add_node("B").expect("can't add node");
+        system.add_node("C").expect("can't add node");
+        system.add_edge("E1", "A", "B", Minute(30)).expect("can't add edge");
+        assert!(system
+            .find_least_time_route(&NodeId("A".into()), &NodeId("C".into()))
+            .is_none());
+
+        system.add_edge("E2", "B", "C", Minute(10)).expect("can't add edge");
+        let route = system
+            .find_least_time_route(&NodeId("A".into()), &NodeId("C".into()))
+            .expect("no route");
+        assert_eq!(route.travel_time, Minute(40));
+
+        system.add_node("D").expect("can't add node");
+        system.add_edge("E3", "A", "D", Minute(5)).expect("can't add edge");
+        system.add_edge("E4", "D", "C", Minute(5)).expect("can't add edge");
+        let route = system
+            .find_least_time_route(&NodeId("A".into()), &NodeId("C".into()))
+            .expect("no route");
+        assert_eq!(route.travel_time, Minute(10));
+        assert_eq!(route.next_hop(), Some(&NodeId("D".into())));
+    }
+
+    struct ScriptedDispatch(Vec<Vec<TrainAction>>);
+
+    impl DispatchStrategy for ScriptedDispatch {
+        fn dispatch(&mut self, _system: &TrainFreightSystem) -> Vec<TrainAction> {
+            if self.0.is_empty() {
+                return vec![];
+            }
+            self.0.remove(0)
+        }
+    }
+
+    #[test]
+    fn test_custom_dispatch_strategy() {
+        let mut system = TrainFreightSystem::default();
+        system.add_node("A").expect("can't add node");
+        system.add_node("B").expect("can't add node");
+        system.add_node("C").expect("can't add node");
+        system.add_edge("E1", "A", "B", Minute(30)).expect("can't add edge");
+        system.add_edge("E2", "C", "B", Minute(10)).expect("can't add edge");
+        system.add_package("K1", Kilogram(5), "A", "C").expect("Can't add package");
+        system.add_train("Q1", Kilogram(6), "B").expect("Can't add train");
+
+        let train = TrainId("Q1".into());
+        let mut strategy = ScriptedDispatch(vec![
+            vec![TrainAction::Move(train.clone(), EdgeId("E1".into()))],
+            vec![
+                TrainAction::Load(train.clone(), PackageId("K1".into())),
+                TrainAction::Move(train.clone(), EdgeId("E1".into())),
+            ],
+            vec![
+                TrainAction::Load(train.clone(), PackageId("K1".into())),
+                TrainAction::Move(train.clone(), EdgeId("E2".into())),
+            ],
+        ]);
+        let total_travel_time = system
+            .deliver_packages(&mut strategy)
+            .expect("can't deliver packages")
+            .total_time;
+        assert_eq!(total_travel_time, Minute(70));
+
+        // E1 doesn't leave C
+        system.add_package("K2", Kilogram(5), "C", "A").expect("Can't add package");
+        let mut strategy = ScriptedDispatch(vec![vec![TrainAction::Move(
+            train,
+            EdgeId("E1".into()),
+        )]]);
+        assert!(matches!(
+            system.deliver_packages(&mut strategy),
+            Err(Error::NoEdge { from, edge }) if from.0 == "C" && edge.0 == "E1"
+        ));
+    }
+
+    #[test]
+    fn test_parallel_edges() {
+        let mut system = TrainFreightSystem::default();
+        for node in ["A", "B", "C"] {
+            system.add_node(node).expect("can't add node");
+        }
+        system.add_edge("L1", "A", "B", Minute(30)).expect("can't add edge");
+        system.add_edge("X1", "A", "B", Minute(10)).expect("can't add edge");
+        system.add_edge("L2", "B", "C", Minute(10)).expect("can't add edge");
+        system.add_package("K1", Kilogram(5), "A", "C").expect("Can't add package");
+        system.add_train("Q1", Kilogram(5), "A").expect("Can't add train");
+
+        let route = system
+            .find_least_time_route(&NodeId("A".into()), &NodeId("C".into()))
+            .expect("no route");
+        assert_eq!(route.edges, vec![EdgeId("X1".into()), EdgeId("L2".into())]);
+        assert_eq!(route.travel_time, Minute(20));
+
+        assert_eq!(system.find_route("A", "C").expect("no station"), Some(route));
+        assert!(matches!(
+            system.find_route("A", "Z"),
+            Err(Error::UnknownNode(node)) if node.0 == "Z"
+        ));
+
+        let mut resumed = system.clone();
+        let plan = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+        let edges: Vec<&str> = plan
+            .moves
+            .iter()
+            .map(|train_move| train_move.edge.0.as_str())
+            .collect();
+        assert_eq!(edges, vec!["X1", "L2"]);
+        assert_eq!(plan.total_time, Minute(20));
+
+        // The local line takes its own travel time
+        let train = TrainId("Q1".into());
+        let mut strategy = ScriptedDispatch(vec![
+            vec![
+                TrainAction::Load(train.clone(), PackageId("K1".into())),
+                TrainAction::Move(train.clone(), EdgeId("L1".into())),
+            ],
+            vec![
+                TrainAction::Load(train.clone(), PackageId("K1".into())),
+                TrainAction::Move(train, EdgeId("L2".into())),
+            ],
+        ]);
+        let plan = resumed.deliver_packages(&mut strategy).expect("can't deliver packages");
+        assert_eq!(plan.moves[0].edge, EdgeId("L1".into()));
+        assert_eq!(plan.moves[0].arrival, Minute(30));
+        assert_eq!(plan.total_time, Minute(40));
+
+        // A train already on the local line holds its track, the express
+        // line stays free
+        system.set_edge_tracks("L1", 1).expect("can't set tracks");
+        system.set_edge_tracks("X1", 1).expect("can't set tracks");
+        let (a, b) = (NodeId("A".into()), NodeId("B".into()));
+        system.train_handler.trains[0].move_to(&a, &b, &EdgeId("L1".into()), Minute(10));
+        system.add_train("Q2", Kilogram(5), "A").expect("Can't add train");
+        system.add_package("K2", Kilogram(5), "A", "B").expect("Can't add package");
+        let plan = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+        assert_eq!(plan.delivery_time(&PackageId("K2".into())), Some(&Minute(10)));
+
+        // Only the slower line the train is on can't be removed
+        system.train_handler.trains[0].move_to(&a, &b, &EdgeId("L1".into()), Minute(30));
+        assert!(matches!(
+            system.remove_edge("L1"),
+            Err(Error::EdgeInUse(edge)) if edge.0 == "L1"
+        ));
+        system.remove_edge("X1").expect("can't remove edge");
+    }
+
+    #[test]
+    fn test_platforms() {
+        let mut system = TrainFreightSystem::default();
+        for node in ["A", "B", "C"] {
+            system.add_node(node).expect("can't add node");
+        }
+        system.add_edge("E1", "A", "B", Minute(30)).expect("can't add edge");
+        system.add_edge("E2", "B", "C", Minute(10)).expect("can't add edge");
+        system.set_node_platforms("B", 1).expect("can't set platforms");
+        system.add_package("K1", Kilogram(5), "A", "B").expect("Can't add package");
+        system.add_train("Q1", Kilogram(5), "A").expect("Can't add train");
+        system.add_train("Q2", Kilogram(5), "B").expect("Can't add train");
+
+        // Q2 at B makes room for Q1 bringing K1 there
+        let mut made_room = system.clone();
+        let plan = made_room.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+        assert_eq!(plan.delivery_time(&PackageId("K1".into())), Some(&Minute(30)));
+        assert!(plan.waits.is_empty());
+        assert_eq!(
+            made_room.train_handler.get_train(&TrainId("Q2".into())).unwrap().status,
+            train::Status::StoppedAt(NodeId("C".into()))
+        );
+
+        assert!(matches!(
+            system.add_train("Q3", Kilogram(5), "B"),
+            Err(Error::StationFull(node)) if node.0 == "B"
+        ));
+        assert!(matches!(
+            system.set_node_platforms("A", 0),
+            Err(Error::NoPlatforms(node)) if node.0 == "A"
+        ));
+
+        // Q2 stays until K2 is ready, Q1 queues in the meantime
+        system.add_package("K2", Kilogram(5), "B", "C").expect("Can't add package");
+        system
+            .set_package_time_window("K2", Some(Minute(40)), None)
+            .expect("Can't set time window");
+        let (q1, q2) = (TrainId("Q1".into()), TrainId("Q2".into()));
+        let mut strategy = ScriptedDispatch(vec![
+            vec![
+                TrainAction::Load(q1.clone(), PackageId("K1".into())),
+                TrainAction::Move(q1.clone(), EdgeId("E1".into())),
+            ],
+            vec![TrainAction::Wait(q2.clone())],
+            vec![
+                TrainAction::Load(q2.clone(), PackageId("K2".into())),
+                TrainAction::Move(q2, EdgeId("E2".into())),
+            ],
+        ]);
+        let plan = system.deliver_packages(&mut strategy).expect("can't deliver packages");
+        assert_eq!(
+            plan.waits,
+            vec![TrainWait {
+                train: q1,
+                station: NodeId("B".into()),
+                edge: EdgeId("E1".into()),
+                from: Minute(30),
+                until: Minute(40),
+                reason: WaitReason::StationFull,
+            }]
+        );
+        assert_eq!(plan.delivery_time(&PackageId("K1".into())), Some(&Minute(40)));
+        assert_eq!(plan.total_time, Minute(50));
+    }
+
+    #[test]
+    fn test_directed_edges() {
+        let mut system = TrainFreightSystem::default();
+        for node in ["A", "B", "C"] {
+            system.add_node(node).expect("can't add node");
+        }
+        // Uphill to B, downhill back
+        system.add_directed_edge("E1", "A", "B", Minute(40)).expect("can't add edge");
+        system.add_directed_edge("E1", "B", "A", Minute(20)).expect("can't add edge");
+        system.add_directed_edge("E2", "B", "C", Minute(10)).expect("can't add edge");
+        assert!(matches!(
+            system.add_directed_edge("E1", "A", "C", Minute(10)),
+            Err(Error::DuplicateEdge(edge)) if edge.0 == "E1"
+        ));
+        system.add_package("K1", Kilogram(5), "B", "A").expect("Can't add package");
+        system.add_package("K2", Kilogram(5), "C", "A").expect("Can't add package");
+        system.add_train("Q1", Kilogram(5), "A").expect("Can't add train");
+
+        let scenario = system.to_scenario();
+        let directed: Vec<(&str, &str, bool)> = scenario
+            .edges
+            .iter()
+            .map(|edge| (edge.node_1.0.as_str(), edge.node_2.0.as_str(), edge.directed))
+            .collect();
+        assert_eq!(directed, vec![("A", "B", true), ("B", "A", true), ("B", "C", true)]);
+        let loaded = TrainFreightSystem::from_scenario(scenario.clone()).expect("can't load");
+        assert_eq!(loaded.to_scenario(), scenario);
+
+        let plan = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+        assert_eq!(plan.delivery_time(&PackageId("K1".into())), Some(&Minute(60)));
+        // No edge out of C
+        let package = system.package_handler.get_package(&PackageId("K2".into())).unwrap();
+        assert_eq!(
+            package.status,
+            package::Status::Unreachable(NodeId("C".into()), UnreachableReason::NoRoute)
+        );
+
+        // Uphill gets slower, downhill keeps its time
+        assert!(matches!(
+            system.set_edge_travel_time("E1", None, Minute(50)),
+            Err(Error::OneWayEdge(edge)) if edge.0 == "E1"
+        ));
+        system.set_edge_travel_time("E1", Some("A"), Minute(50)).expect("can't update edge");
+        let (a, b) = (NodeId("A".into()), NodeId("B".into()));
+        assert_eq!(system.route_table().travel_time(&a, &b), Some(Minute(50)));
+        assert_eq!(system.route_table().travel_time(&b, &a), Some(Minute(20)));
+    }
+
+    fn timetable(stops: &[(&str, u32)], period: Option<Minute>) -> Option<Timetable> {
+        let stops = stops
+            .iter()
+            .map(|(station, time)| (NodeId((*station).into()), Minute(*time)))
+            .collect();
+        Some(Timetable::new(stops, period))
+    }
+
+    #[test]
+    fn test_timetable() {
+        let mut system = TrainFreightSystem::default();
+        for node in ["A", "B", "C"] {
+            system.add_node(node).expect("can't add node");
+        }
+        system.add_edge("E1", "A", "B", Minute(30)).expect("can't add edge");
+        system.add_edge("E2", "B", "C", Minute(10)).expect("can't add edge");
+        system.add_train("Q1", Kilogram(6), "A").expect("Can't add train");
+        system.add_train("Q2", Kilogram(10), "C").expect("Can't add train");
+
+        assert!(matches!(
+            system.set_train_timetable("Q9", timetable(&[("A", 0), ("B", 30)], None)),
+            Err(Error::UnknownTrain(train)) if train.0 == "Q9"
+        ));
+        for (stops, period) in [
+            (&[("A", 0)][..], None),
+            (&[("B", 0), ("C", 10)][..], None),
+            (&[("A", 0), ("B", 30)][..], Some(Minute(60))),
+            (&[("A", 0), ("B", 30), ("A", 60)][..], Some(Minute(50))),
+        ] {
+            assert!(matches!(
+                system.set_train_timetable("Q1", timetable(stops, period)),
+                Err(Error::InvalidTimetable(train)) if train.0 == "Q1"
+            ));
+        }
+        for stops in [&[("A", 60), ("B", 80)][..], &[("A", 60), ("C", 100)][..]] {
+            assert!(matches!(
+                system.set_train_timetable("Q1", timetable(stops, None)),
+                Err(Error::TimetableLeg { train, from, .. }) if train.0 == "Q1" && from.0 == "A"
+            ));
+        }
+        let stops = [("A", 60), ("B", 90), ("C", 100)];
+        system.set_train_timetable("Q1", timetable(&stops, None)).expect("can't set timetable");
+        let mut simulation = Simulation::default();
+        let action = TrainAction::Move(TrainId("Q1".into()), EdgeId("E1".into()));
+        assert!(matches!(
+            system.apply_train_action(&mut simulation, &action),
+            Err(Error::TrainScheduled(train)) if train.0 == "Q1"
+        ));
+
+        // K2 doesn't fit next to K1 on the service, an on-demand train takes it
+        system.add_package("K1", Kilogram(5), "A", "C").expect("Can't add package");
+        system.add_package("K2", Kilogram(4), "A", "B").expect("Can't add package");
+        system.add_package("K3", Kilogram(1), "B", "C").expect("Can't add package");
+        let plan = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+        let service: Vec<(Minute, &str, Vec<PackageId>)> = plan
+            .moves
+            .iter()
+            .filter(|train_move| train_move.train.0 == "Q1")
+            .map(|train_move| {
+                let origin = train_move.origin.0.as_str();
+                (train_move.departure.clone(), origin, train_move.picked_up.clone())
+            })
+            .collect();
+        assert_eq!(
+            service,
+            vec![
+                (Minute(60), "A", vec![PackageId("K1".into())]),
+                (Minute(90), "B", vec![PackageId("K1".into()), PackageId("K3".into())]),
+            ]
+        );
+        for (package, train) in [("K1", "Q1"), ("K2", "Q2"), ("K3", "Q1")] {
+            let delivery = plan
+                .deliveries
+                .iter()
+                .find(|delivery| delivery.package.0 == package)
+                .expect("package not delivered");
+            assert_eq!(delivery.train.0, train);
+        }
+        assert_eq!(plan.delivery_time(&PackageId("K1".into())), Some(&Minute(100)));
+
+        // Dispatched on demand once the timetable is over
+        assert!(!system.train_handler.trains[0].is_scheduled());
+        assert_eq!(system.train_handler.trains[0].get_location(), Some(NodeId("C".into())));
+    }
+
+    #[test]
+    fn test_repeated_timetable() {
+        let mut system = TrainFreightSystem::default();
+        for node in ["A", "B", "C"] {
+            system.add_node(node).expect("can't add node");
+        }
+        system.add_edge("E1", "A", "B", Minute(30)).expect("can't add edge");
+        system.add_edge("E2", "B", "C", Minute(10)).expect("can't add edge");
+        system.add_train("Q1", Kilogram(6), "A").expect("Can't add train");
+        let stops = [("A", 0), ("B", 30), ("A", 60)];
+        system.set_train_timetable("Q1", timetable(&stops, Some(Minute(120)))).expect("can't set timetable");
+        system.add_package("K1", Kilogram(5), "B", "A").expect("Can't add package");
+        // Off the line of the only train
+        system.add_package("K2", Kilogram(5), "A", "C").expect("Can't add package");
+
+        let plan = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+        assert_eq!(plan.delivery_time(&PackageId("K1".into())), Some(&Minute(60)));
+        assert_eq!(plan.total_time, Minute(60));
+        let package = system.package_handler.get_package(&PackageId("K2".into())).unwrap();
+        assert_eq!(
+            package.status,
+            package::Status::Unreachable(NodeId("A".into()), UnreachableReason::NoTrain)
+        );
+
+        let loaded = TrainFreightSystem::from_scenario(system.to_scenario()).expect("can't load");
+        assert_eq!(loaded.train_handler.trains, system.train_handler.trains);
+
+        // Runs again two hours after it first left, an hour into this run
+        system.remove_package("K2").expect("can't remove package");
+        system.add_package("K3", Kilogram(5), "A", "B").expect("Can't add package");
+        let plan = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+        assert_eq!(plan.moves[0].departure, Minute(60));
+        assert_eq!(plan.delivery_time(&PackageId("K3".into())), Some(&Minute(90)));
+        assert!(system.train_handler.trains[0].is_scheduled());
+    }
+
+    #[test]
+    fn test_timetable_next_round() {
+        let mut system = TrainFreightSystem::default();
+        for node in ["A", "B", "C"] {
+            system.add_node(node).expect("can't add node");
+        }
+        system.add_edge("E1", "A", "B", Minute(10)).expect("can't add edge");
+        system.add_edge("E2", "B", "C", Minute(10)).expect("can't add edge");
+        system.add_edge("E3", "C", "A", Minute(10)).expect("can't add edge");
+        system.add_train("Q1", Kilogram(6), "A").expect("Can't add train");
+        let stops = [("A", 0), ("B", 10), ("C", 20), ("A", 30)];
+        system.set_train_timetable("Q1", timetable(&stops, Some(Minute(60)))).expect("can't set timetable");
+        // B is passed before the train gets to C, it is reached in the next
+        // round
+        system.add_package("K1", Kilogram(5), "C", "B").expect("Can't add package");
+
+        let plan = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+        assert_eq!(plan.delivery_time(&PackageId("K1".into())), Some(&Minute(70)));
+        assert_eq!(plan.total_time, Minute(70));
+    }
+}
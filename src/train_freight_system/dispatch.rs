@@ -0,0 +1,402 @@
+use super::{
+    edge::{Edge, EdgeId},
+    node::NodeId,
+    package::{Package, PackageHandler, PackageId, Priority},
+    route::Route,
+    train::{TrainHandler, TrainId},
+    DeliveryResult, TrainFreightSystem,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrainAction {
+    // Load package(PackageId) dropped at the station where train(TrainId) is stopped
+    Load(TrainId, PackageId),
+    // Move train(TrainId) along the edge(EdgeId) out of its station
+    Move(TrainId, EdgeId),
+    // Keep train(TrainId) at its station until the next train arrives
+    Wait(TrainId),
+}
+
+/// Decides what the stopped trains do. Called by `deliver_packages` at the
+/// start and every time a train arrives; the returned actions are applied in
+/// order.
+pub trait DispatchStrategy {
+    fn dispatch(&mut self, system: &TrainFreightSystem) -> Vec<TrainAction>;
+}
+
+/// Biggest train first, longest route first, with a detour to pick up
+/// packages near the route while the train still has room.
+#[derive(Debug, Default)]
+pub struct DefaultDispatch;
+
+impl DispatchStrategy for DefaultDispatch {
+    fn dispatch(&mut self, system: &TrainFreightSystem) -> Vec<TrainAction> {
+        let mut planner = Planner::new(system, PackageOrder::LongestRoute);
+        planner.deliver_packages_in_nodes();
+        planner.actions
+    }
+}
+
+/// Same as `DefaultDispatch`, but the packages with the least slack before
+/// their deadline go first, then the ones without deadline by longest route.
+#[derive(Debug, Default)]
+pub struct DeadlineDispatch;
+
+impl DispatchStrategy for DeadlineDispatch {
+    fn dispatch(&mut self, system: &TrainFreightSystem) -> Vec<TrainAction> {
+        let mut planner = Planner::new(system, PackageOrder::LeastSlack);
+        planner.deliver_packages_in_nodes();
+        planner.actions
+    }
+}
+
+// Which package at a station the train is sent for first
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PackageOrder {
+    LongestRoute,
+    LeastSlack,
+}
+
+// Works on a copy of the trains and packages so every decision sees the ones
+// taken before it, and records them as actions.
+struct Planner<'a> {
+    system: &'a TrainFreightSystem,
+    train_handler: TrainHandler,
+    package_handler: PackageHandler,
+    order: PackageOrder,
+    actions: Vec<TrainAction>,
+}
+
+impl<'a> Planner<'a> {
+    fn new(system: &'a TrainFreightSystem, order: PackageOrder) -> Self {
+        // Packages booked on a scheduled train are left to it
+        let mut package_handler = system.package_handler.clone();
+        package_handler
+            .packages
+            .retain(|package| !system.is_booked(&package.id));
+        Self {
+            system,
+            train_handler: system.train_handler.clone(),
+            package_handler,
+            order,
+            actions: vec![],
+        }
+    }
+
+    // Time left before the deadline once delivered the fastest way, leaving
+    // out the current time as it is the same for every package.
+    fn get_slack(&self, package_id: &PackageId) -> Option<i64> {
+        let package = self.package_handler.get_package(package_id).unwrap();
+        let deadline = package.deadline.as_ref()?;
+        let travel_time = self
+            .system
+            .route_table()
+            .travel_time(package.get_location()?, &package.destination)?;
+        Some(i64::from(deadline.0) - i64::from(travel_time.0))
+    }
+
+    fn get_priority(&self, package_id: &PackageId) -> Priority {
+        self.package_handler.get_package(package_id).unwrap().priority
+    }
+
+    // Higher priority classes always go first, the order only breaks ties
+    // within a class.
+    fn goes_before(&self, package: (&PackageId, &Route), other: (&PackageId, &Route)) -> bool {
+        let (priority, other_priority) = (self.get_priority(package.0), self.get_priority(other.0));
+        if priority != other_priority {
+            return priority < other_priority;
+        }
+
+        let is_longer = package.1.nodes.len() > other.1.nodes.len();
+        if self.order == PackageOrder::LongestRoute {
+            return is_longer;
+        }
+
+        match (self.get_slack(package.0), self.get_slack(other.0)) {
+            (Some(slack), Some(other_slack)) => {
+                slack < other_slack || (slack == other_slack && is_longer)
+            }
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => is_longer,
+        }
+    }
+
+    fn load_package(&mut self, train_id: &TrainId, package_id: &PackageId) {
+        let package = self.package_handler.get_package_mut(package_id).unwrap();
+        self.train_handler.load_package(train_id, package);
+        if package.is_package_loaded_in_train(train_id) {
+            self.actions
+                .push(TrainAction::Load(train_id.clone(), package_id.clone()));
+        }
+    }
+
+    fn find_edge(&self, origin: &NodeId, edge_id: &EdgeId) -> &'a Edge {
+        let system = self.system;
+        let pos = system.find_node_index_by_id(origin).unwrap();
+        system.nodes[pos].find_edge_by_id(edge_id).unwrap()
+    }
+
+    fn move_train(&mut self, train_id: &TrainId, origin: &NodeId, edge: &Edge) {
+        self.train_handler.move_to_node(
+            train_id,
+            origin,
+            &edge.node,
+            &edge.id,
+            edge.travel_time.clone(),
+        );
+        self.actions
+            .push(TrainAction::Move(train_id.clone(), edge.id.clone()));
+    }
+
+    // Counting the trains on their way, so that no other train is sent there
+    // to queue
+    fn has_free_platform(&self, node_id: &NodeId) -> bool {
+        let node = self
+            .system
+            .nodes
+            .iter()
+            .find(|node| node.id == *node_id)
+            .unwrap();
+        node.platforms.is_none_or(|platforms| {
+            self.train_handler.count_trains_at_node(node_id, true) < platforms as usize
+        })
+    }
+
+    // A train queued for a platform gets the one of a train left with nothing
+    // to do, moved to the next station with room, the one the queued train
+    // comes from last.
+    fn make_room_for_queued_trains(&mut self) {
+        let system = self.system;
+        for (_, origin, station) in self.train_handler.list_queued_trains() {
+            let Some(train_id) = self
+                .train_handler
+                .list_stopped_trains_at_node(&station)
+                .first()
+                .cloned()
+            else {
+                continue;
+            };
+            let node = system.nodes.iter().find(|node| node.id == station).unwrap();
+            let mut edges: Vec<&Edge> = node
+                .edges
+                .iter()
+                .filter(|edge| self.has_free_platform(&edge.node))
+                .collect();
+            edges.sort_by_key(|edge| edge.node == origin);
+            if let Some(edge) = edges.first() {
+                self.move_train(&train_id, &station, edge);
+            }
+        }
+    }
+
+    fn list_all_undelivered_packages_least_time_routes(&self) -> Vec<Route> {
+        let packages = self.package_handler.list_undelivered_packages();
+        let mut packages_routes: Vec<Route> = vec![];
+        for package_id in &packages {
+            let package = self.package_handler.get_package(package_id).unwrap();
+            if let Some(route) = self.system.get_least_time_route_to_deliver_package(package) {
+                packages_routes.push(route);
+            }
+        }
+        packages_routes
+    }
+
+    fn deliver_packages_in_node(&mut self, node_id: &NodeId) -> DeliveryResult {
+        let packages = self
+            .package_handler
+            .list_undelivered_packages_at_node(node_id);
+        if packages.is_empty() {
+            return DeliveryResult::NoPackages;
+        }
+
+        let biggest_train = if let Some(biggest_train) = self
+            .train_handler
+            .find_largest_capacity_train_in_node(node_id)
+        {
+            biggest_train
+            // load packages going to
+        } else {
+            return DeliveryResult::NoTrains;
+        };
+
+        let mut highest: Option<(&PackageId, Route)> = None;
+        for package_id in packages.iter() {
+            let package = self.package_handler.get_package(package_id).unwrap();
+            if let Some(route) = self.system.get_least_time_route_to_deliver_package(package) {
+                if highest.as_ref().is_none_or(|(highest_package, highest_route)| {
+                    self.goes_before((package_id, &route), (highest_package, highest_route))
+                }) {
+                    highest = Some((package_id, route));
+                }
+            }
+        }
+
+        let Some((_, highest_route)) = highest else {
+            return DeliveryResult::NoRoutes;
+        };
+        let (Some(destination), Some(edge_id)) =
+            (highest_route.next_hop(), highest_route.next_edge())
+        else {
+            return DeliveryResult::NoRoutes;
+        };
+        // Loaded in that order, so when the train fills up the lower
+        // priority packages are the ones left to wait
+        let mut filtered_packages = self.get_packages_passing_to_node(destination, &packages);
+        filtered_packages.sort_by_key(|package_id| {
+            let slack = match self.order {
+                PackageOrder::LongestRoute => None,
+                PackageOrder::LeastSlack => self.get_slack(package_id),
+            };
+            (self.get_priority(package_id), slack.is_none(), slack)
+        });
+        // Before loading packages, check if there are packages closer to
+        // this route that can still fit in this train.
+        // if there are then move train to that direction
+        let all_routes = self.list_all_undelivered_packages_least_time_routes();
+        for route in &all_routes {
+            let diff: Vec<&NodeId> = route
+                .nodes
+                .iter()
+                .filter(|node_id| !highest_route.contains(node_id))
+                .rev()
+                .collect();
+            for check_node_id in diff.clone() {
+                let time1 = self
+                    .system
+                    .get_travel_time_from_routes(&[check_node_id.clone(), node_id.clone()]);
+                if time1 < highest_route.travel_time {
+                    let packages = self
+                        .package_handler
+                        .list_undelivered_packages_at_node(check_node_id);
+                    for package_id in &packages {
+                        let train = self.train_handler.get_train(&biggest_train).unwrap();
+                        let this_package = self.package_handler.get_package(package_id).unwrap();
+                        if train.can_accomodate_package(this_package) {
+                            let Some(edge_id) =
+                                self.system.route_table().next_edge(node_id, diff[0])
+                            else {
+                                continue;
+                            };
+                            let edge = self.find_edge(node_id, edge_id);
+                            self.move_train(&biggest_train, node_id, edge);
+                            return DeliveryResult::TrainPicking;
+                        }
+                    }
+                }
+            }
+        }
+
+        for package_id in filtered_packages.iter() {
+            self.load_package(&biggest_train, package_id);
+        }
+
+        let edge = self.find_edge(node_id, edge_id);
+        self.move_train(&biggest_train, node_id, edge);
+
+        if !self
+            .package_handler
+            .list_undelivered_packages_at_node(node_id)
+            .is_empty()
+        {
+            return DeliveryResult::NotAllPackageLoaded;
+        }
+
+        DeliveryResult::AllPackageLoaded
+    }
+
+    fn get_packages_passing_to_node(
+        &self,
+        node_id: &NodeId,
+        packages: &[PackageId],
+    ) -> Vec<PackageId> {
+        let mut filtered_packages: Vec<PackageId> = vec![];
+        for package_id in packages {
+            let package = self.package_handler.get_package(package_id).unwrap();
+            if self
+                .system
+                .route_table()
+                .travel_time(node_id, &package.destination)
+                .is_some()
+            {
+                filtered_packages.push(package_id.clone());
+            }
+        }
+        filtered_packages
+    }
+
+    fn deliver_packages_in_nodes(&mut self) {
+        let system = self.system;
+        for node in &system.nodes {
+            loop {
+                let result = self.deliver_packages_in_node(&node.id);
+                match result {
+                    DeliveryResult::NoPackages
+                    | DeliveryResult::NoTrains
+                    | DeliveryResult::NoRoutes
+                    | DeliveryResult::AllPackageLoaded
+                    | DeliveryResult::TrainPicking => break,
+                    DeliveryResult::NotAllPackageLoaded => continue,
+                }
+            }
+        }
+        // have packages not picked up and have trains not moving
+        let dropped_packages = self.package_handler.list_undelivered_packages();
+        for package_id in &dropped_packages {
+            let package: Package = self.package_handler.get_package(package_id).unwrap().clone();
+            let Some(route) = self.system.get_least_time_route_to_deliver_package(&package) else {
+                continue;
+            };
+            let routes = &route.nodes;
+
+            let mut has_trains_moved = false;
+            for i in 1..routes.len() {
+                // Back along the route, or the fastest way to its start when
+                // the edge is one-way
+                let pos = system.find_node_index_by_id(&routes[i]).unwrap();
+                let back = system.nodes[pos]
+                    .find_edge_with_node(&routes[i - 1])
+                    .or_else(|| {
+                        let edge_id = system.route_table().next_edge(&routes[i], &routes[0])?;
+                        Some(self.find_edge(&routes[i], edge_id))
+                    });
+                let Some(back) = back else {
+                    continue;
+                };
+                for train_id in &self.train_handler.list_stopped_trains_at_node(&routes[i]) {
+                    let train = self.train_handler.get_train(train_id).unwrap();
+                    if train.can_accomodate_package(&package) && self.has_free_platform(&back.node)
+                    {
+                        self.move_train(train_id, &routes[i], back);
+                        has_trains_moved = true;
+                        break;
+                    }
+                }
+            }
+
+            // find trains not the path
+            if !has_trains_moved {
+                let train_ids = self.train_handler.list_stopped_trains();
+                for train_id in &train_ids {
+                    let train = self.train_handler.get_train(train_id).unwrap();
+                    let train_location = train.get_location().unwrap();
+                    let Some(edge_id) = self
+                        .system
+                        .route_table()
+                        .next_edge(&train_location, &package.destination)
+                    else {
+                        continue;
+                    };
+                    let edge = self.find_edge(&train_location, edge_id);
+                    if train.can_accomodate_package(&package) && self.has_free_platform(&edge.node)
+                    {
+                        self.move_train(train_id, &train_location, edge);
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.make_room_for_queued_trains();
+    }
+}
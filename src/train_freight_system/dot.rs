@@ -0,0 +1,122 @@
+use std::{fs, path::Path};
+
+use super::{error::Result, node::Node, TrainFreightSystem};
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", escape(text))
+}
+
+// Quoted label with a line break between the lines
+fn quote_lines(lines: &[String]) -> String {
+    let lines: Vec<String> = lines.iter().map(|line| escape(line)).collect();
+    format!("\"{}\"", lines.join("\\n"))
+}
+
+fn node_label(system: &TrainFreightSystem, node: &Node, positions: bool) -> Vec<String> {
+    let mut lines = vec![node.id.0.clone()];
+    if !positions {
+        return lines;
+    }
+
+    for train in &system.train_handler.trains {
+        if train.get_location().as_ref() == Some(&node.id) {
+            lines.push(format!(
+                "{} ({}/{})",
+                train.id.0, train.load_size.0, train.max_capacity.0
+            ));
+        }
+    }
+    for package in &system.package_handler.packages {
+        if package.get_station() == Some(&node.id) {
+            lines.push(format!(
+                "{} ({} to {})",
+                package.id.0, package.weight.0, package.destination.0
+            ));
+        }
+    }
+    lines
+}
+
+/// Graphviz digraph of the stations and edges, labelled with their names and
+/// travel times, two-way edges drawn without arrows. With `positions` the
+/// stations also list the trains stopped and the packages waiting there.
+pub fn render(system: &TrainFreightSystem, positions: bool) -> String {
+    let mut dot = String::from("digraph network {\n");
+    for node in &system.nodes {
+        dot += &format!(
+            "    {} [label={}];\n",
+            quote(&node.id.0),
+            quote_lines(&node_label(system, node, positions))
+        );
+    }
+
+    for (pos, node) in system.nodes.iter().enumerate() {
+        for edge in &node.edges {
+            // Two-way edges are on both of their nodes, drawn from the first
+            let other = system.find_node_index_by_id(&edge.node).unwrap();
+            let has_way_back = system.nodes[other].edges.iter().any(|back| {
+                back.id == edge.id && back.node == node.id && back.travel_time == edge.travel_time
+            });
+            if has_way_back && other < pos {
+                continue;
+            }
+
+            let mut label = vec![edge.id.0.clone(), edge.travel_time.0.to_string()];
+            if let Some(tracks) = edge.tracks {
+                label.push(format!("{tracks} track(s)"));
+            }
+            let direction = if has_way_back { ", dir=none" } else { "" };
+            dot += &format!(
+                "    {} -> {} [label={}{}];\n",
+                quote(&node.id.0),
+                quote(&edge.node.0),
+                quote_lines(&label),
+                direction
+            );
+        }
+    }
+    dot += "}\n";
+    dot
+}
+
+pub fn write(system: &TrainFreightSystem, positions: bool, path: impl AsRef<Path>) -> Result<()> {
+    fs::write(path, render(system, positions))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{kilogram::Kilogram, minute::Minute};
+
+    #[test]
+    fn test_render() {
+        let mut system = TrainFreightSystem::default();
+        for node in ["A", "B", "C\"1"] {
+            system.add_node(node).expect("can't add node");
+        }
+        system.add_edge("E1", "A", "B", Minute(30)).expect("can't add edge");
+        system.set_edge_tracks("E1", 1).expect("can't set tracks");
+        system
+            .add_directed_edge("E2", "B", "C\"1", Minute(10))
+            .expect("can't add edge");
+        system.add_train("Q1", Kilogram(6), "A").expect("Can't add train");
+        system.add_package("K1", Kilogram(5), "A", "B").expect("Can't add package");
+
+        assert_eq!(
+            render(&system, false),
+            "digraph network {\n\
+             \x20   \"A\" [label=\"A\"];\n\
+             \x20   \"B\" [label=\"B\"];\n\
+             \x20   \"C\\\"1\" [label=\"C\\\"1\"];\n\
+             \x20   \"A\" -> \"B\" [label=\"E1\\n30\\n1 track(s)\", dir=none];\n\
+             \x20   \"B\" -> \"C\\\"1\" [label=\"E2\\n10\"];\n\
+             }\n"
+        );
+        assert!(render(&system, true).contains("\"A\" [label=\"A\\nQ1 (0/6)\\nK1 (5 to B)\"];"));
+    }
+}
@@ -1,15 +1,20 @@
+use serde::{Deserialize, Serialize};
+
 use crate::util::minute::Minute;
 
 use super::node::NodeId;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EdgeId(pub String);
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Edge {
     pub id: EdgeId,
     pub node: NodeId,
     pub travel_time: Minute,
+    // Trains that can be on the edge at once in either direction, any number
+    // when None
+    pub tracks: Option<u32>,
 }
 
 impl Edge {
@@ -18,6 +23,7 @@ impl Edge {
             id: EdgeId(name.into()),
             node,
             travel_time,
+            tracks: None,
         }
     }
 }
@@ -1,34 +1,200 @@
 use std::fmt;
 
-pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+use super::{
+    edge::EdgeId,
+    node::NodeId,
+    package::PackageId,
+    solver::{MAX_PACKAGES, MAX_TRAINS},
+    train::TrainId,
+};
 
-#[derive(Debug)]
-pub enum ErrorKind {
-    AddStationError,
-    AddEdgeError,
-    AddTrainError,
-    AddPackageError,
-}
+pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug)]
-pub struct Error {
-    pub kind: ErrorKind,
-    pub msg: String,
+pub enum Error {
+    // Station(NodeId) added twice
+    DuplicateNode(NodeId),
+    // Station(NodeId) not in the network
+    UnknownNode(NodeId),
+    // Station(NodeId) given no platform for the trains to stop at
+    NoPlatforms(NodeId),
+    // Station(NodeId) has no platform left for another train
+    StationFull(NodeId),
+    // Station(NodeId) removed while trains or packages are still at it or
+    // on their way to or from it
+    NodeInUse(NodeId),
+    // Edge(EdgeId) added twice to the same station
+    DuplicateEdge(EdgeId),
+    // Edge(EdgeId) going from a station to itself
+    SelfLoop(EdgeId),
+    // Edge(EdgeId) not in the network
+    UnknownEdge(EdgeId),
+    // Edge(EdgeId) given no track for the trains to use
+    NoTracks(EdgeId),
+    // Edge(EdgeId) removed while a train is on it
+    EdgeInUse(EdgeId),
+    // Edge(EdgeId) updated without the station it leaves from while both of
+    // its ways are one-way
+    OneWayEdge(EdgeId),
+    // Train(TrainId) added twice
+    DuplicateTrain(TrainId),
+    // Train(TrainId) not in the network
+    UnknownTrain(TrainId),
+    // Train(TrainId) removed while still carrying packages
+    TrainLoaded(TrainId),
+    // Train(TrainId) given less capacity than the weight it carries
+    OverCapacity(TrainId),
+    // Package(PackageId) added twice
+    DuplicatePackage(PackageId),
+    // Package(PackageId) not in the network
+    UnknownPackage(PackageId),
+    // Package can't get from its station to its destination
+    Unreachable {
+        package: PackageId,
+        from: NodeId,
+        to: NodeId,
+    },
+    // Package(PackageId) is heavier or bulkier than every train
+    NoTrainFits(PackageId),
+    // Train(TrainId) was given an action while moving or not available
+    TrainNotStopped(TrainId),
+    // Train(TrainId) was given an action while it keeps to a timetable
+    TrainScheduled(TrainId),
+    // Timetable of train(TrainId) doesn't start where it stops, has less
+    // than two stops, or repeats without going back to its first stop
+    // within its period
+    InvalidTimetable(TrainId),
+    // Timetable of the train has a leg without an edge fast enough for it
+    TimetableLeg {
+        train: TrainId,
+        from: NodeId,
+        to: NodeId,
+    },
+    // Package is loaded to a train stopped at another station
+    PackageNotAtStation { package: PackageId, train: TrainId },
+    // Package is loaded to a train without room left for it
+    TrainFull { package: PackageId, train: TrainId },
+    // Train is moved along an edge that doesn't leave its station
+    NoEdge { from: NodeId, edge: EdgeId },
+    // Plan asked for with packages to deliver but no train
+    NoTrains,
+    // Solver given more trains or packages than it supports
+    InstanceTooLarge { trains: usize, packages: usize },
+    // Solver found no plan delivering every package
+    NoPlan,
+    // Scenario file can't be read or written
+    Io(std::io::Error),
+    // Scenario is not valid JSON
+    Json(serde_json::Error),
 }
 
-impl Error {
-    pub fn new(kind: ErrorKind, msg: impl ToString) -> Self {
-        Self {
-            kind,
-            msg: msg.to_string(),
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Json(err) => Some(err),
+            _ => None,
         }
     }
 }
 
-impl std::error::Error for Error {}
-
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}: {}", self.kind, self.msg)
+        match self {
+            Error::DuplicateNode(node) => write!(f, "Station '{}' already existing", node.0),
+            Error::UnknownNode(node) => write!(f, "Station '{}' doesn't exist", node.0),
+            Error::NoPlatforms(node) => {
+                write!(f, "Station '{}' needs at least one platform", node.0)
+            }
+            Error::StationFull(node) => write!(f, "Station '{}' has no platform left", node.0),
+            Error::NodeInUse(node) => write!(
+                f,
+                "Station '{}' still has trains or packages, force to remove them too",
+                node.0
+            ),
+            Error::DuplicateEdge(edge) => write!(f, "Edge '{}' already existing", edge.0),
+            Error::SelfLoop(edge) => write!(f, "Edge '{}' connects a station to itself", edge.0),
+            Error::UnknownEdge(edge) => write!(f, "Edge '{}' doesn't exist", edge.0),
+            Error::NoTracks(edge) => write!(f, "Edge '{}' needs at least one track", edge.0),
+            Error::EdgeInUse(edge) => write!(f, "Edge '{}' has trains on it", edge.0),
+            Error::OneWayEdge(edge) => write!(
+                f,
+                "Edge '{}' is one-way both ways, give the station it leaves from",
+                edge.0
+            ),
+            Error::DuplicateTrain(train) => write!(f, "Train '{}' already existing", train.0),
+            Error::UnknownTrain(train) => write!(f, "Train '{}' doesn't exist", train.0),
+            Error::TrainLoaded(train) => write!(
+                f,
+                "Train '{}' still carries packages, force to remove them too",
+                train.0
+            ),
+            Error::OverCapacity(train) => {
+                write!(f, "Train '{}' carries more than that capacity", train.0)
+            }
+            Error::DuplicatePackage(package) => {
+                write!(f, "Package '{}' already existing", package.0)
+            }
+            Error::UnknownPackage(package) => write!(f, "Package '{}' doesn't exist", package.0),
+            Error::Unreachable { package, from, to } => write!(
+                f,
+                "Package '{}' can't be moved from '{}' to '{}'",
+                package.0, from.0, to.0
+            ),
+            Error::NoTrainFits(package) => {
+                write!(f, "Package '{}' doesn't fit in any train", package.0)
+            }
+            Error::TrainNotStopped(train) => {
+                write!(f, "Train '{}' is not stopped at a station", train.0)
+            }
+            Error::TrainScheduled(train) => {
+                write!(f, "Train '{}' keeps to its timetable", train.0)
+            }
+            Error::InvalidTimetable(train) => write!(
+                f,
+                "Timetable of train '{}' needs two stops or more from where the train is, \
+                 and to end at its first stop within its period when repeated",
+                train.0
+            ),
+            Error::TimetableLeg { train, from, to } => write!(
+                f,
+                "Train '{}' can't get from '{}' to '{}' in the time of its timetable",
+                train.0, from.0, to.0
+            ),
+            Error::PackageNotAtStation { package, train } => write!(
+                f,
+                "Package '{}' is not at the station of train '{}'",
+                package.0, train.0
+            ),
+            Error::TrainFull { package, train } => write!(
+                f,
+                "Package '{}' doesn't fit in train '{}'",
+                package.0, train.0
+            ),
+            Error::NoEdge { from, edge } => {
+                write!(f, "No edge '{}' out of '{}'", edge.0, from.0)
+            }
+            Error::NoTrains => write!(f, "No trains available"),
+            Error::InstanceTooLarge { trains, packages } => write!(
+                f,
+                "Solver supports up to {MAX_TRAINS} trains and {MAX_PACKAGES} packages, \
+                 got {trains} trains and {packages} packages"
+            ),
+            Error::NoPlan => write!(f, "No plan can deliver all the packages"),
+            Error::Io(err) => write!(f, "{err}"),
+            Error::Json(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
     }
 }
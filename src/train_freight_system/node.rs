@@ -1,15 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+use crate::util::{
+    kilogram::{Kilogram, KILOGRAMS_PER_TONNE},
+    minute::Minute,
+};
+
 use super::{
     edge::{Edge, EdgeId},
-    error::{Error, ErrorKind, Result},
+    error::{Error, Result},
 };
 
-#[derive(Debug, Default, PartialEq, Clone, Hash, Eq)]
+#[derive(Debug, Default, PartialEq, Clone, Hash, Eq, Serialize, Deserialize)]
 pub struct NodeId(pub String);
 
-#[derive(Debug, Default)]
+/// Time a train spends at a station to load or unload its packages, none
+/// when it has nothing to handle there.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HandlingTime {
+    pub per_stop: Minute,
+    pub per_package: Minute,
+    pub per_tonne: Minute,
+}
+
+impl HandlingTime {
+    pub fn get_dwell_time(&self, packages: usize, weight: &Kilogram) -> Minute {
+        if packages == 0 {
+            return Minute(0);
+        }
+        let packages = u32::try_from(packages).unwrap_or(u32::MAX);
+        // Rounded up, a part of a minute still holds the train
+        let per_weight = (u64::from(self.per_tonne.0) * u64::from(weight.0))
+            .div_ceil(u64::from(KILOGRAMS_PER_TONNE));
+        self.per_stop.clone()
+            + Minute(self.per_package.0.saturating_mul(packages))
+            + Minute(u32::try_from(per_weight).unwrap_or(u32::MAX))
+    }
+}
+
+#[derive(Debug, Default, Clone)]
 pub struct Node {
     pub id: NodeId,
     pub edges: Vec<Edge>,
+    pub handling_time: HandlingTime,
+    // Trains that can stop at the station at once, any number when None
+    pub platforms: Option<u32>,
 }
 
 impl Node {
@@ -24,17 +58,21 @@ impl Node {
         self.edges.iter().position(|edge| edge.id == *id)
     }
 
+    pub fn find_edge_by_id(&self, id: &EdgeId) -> Option<&Edge> {
+        self.edges.iter().find(|edge| edge.id == *id)
+    }
+
+    /// Fastest of the edges to the station when there are parallel ones.
     pub fn find_edge_with_node(&self, node_id: &NodeId) -> Option<&Edge> {
-        self.edges.iter().find(|&edge| edge.node == *node_id)
+        self.edges
+            .iter()
+            .filter(|&edge| edge.node == *node_id)
+            .min_by_key(|edge| edge.travel_time.clone())
     }
 
     pub fn add_edge(&mut self, edge: Edge) -> Result<()> {
         if self.find_edge_index_by_id(&edge.id).is_some() {
-            return Err(Error::new(
-                ErrorKind::AddEdgeError,
-                format!("Edge '{:?}' already exising", edge.id),
-            )
-            .into());
+            return Err(Error::DuplicateEdge(edge.id));
         }
         self.edges.push(edge);
         Ok(())
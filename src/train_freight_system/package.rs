@@ -1,8 +1,10 @@
-use crate::util::kilogram::Kilogram;
+use serde::{Deserialize, Serialize};
 
-use super::{error::Error, error::ErrorKind, error::Result, node::NodeId, train::TrainId};
+use crate::util::{kilogram::Kilogram, minute::Minute, volume::Liter};
 
-#[derive(Debug, Default, PartialEq, Clone)]
+use super::{error::Error, error::Result, node::NodeId, train::TrainId};
+
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Status {
     #[default]
     // Delivered and completed, so it wont be reflected in report in succeeding run
@@ -13,19 +15,51 @@ pub enum Status {
     LoadedTo(TrainId),
     //Delivered by train(TrainId)
     Delivered(TrainId),
-    //No trains can carry the package(weight is too heavy), location(nNodeId)
+    //No trains can carry the package(too heavy or too bulky), location(nNodeId)
     CantBeTransported(NodeId),
+    //Package at location(NodeId) can't get to its destination for reason(UnreachableReason)
+    Unreachable(NodeId, UnreachableReason),
+    //Waiting at station(NodeId) for its earliest pickup time
+    NotReady(NodeId),
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum UnreachableReason {
+    // Destination can't be reached from the station of the package
+    NoRoute,
+    // None of the trains that can carry the package are in its part of the
+    // network, or on a timetable calling at its station then its destination
+    NoTrain,
+}
+
+// Declared from the highest class, so sorting by it puts express first
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+pub enum Priority {
+    Express,
+    #[default]
+    Standard,
+    Economy,
 }
 
-#[derive(Debug, Default, PartialEq, Clone)]
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 pub struct PackageId(pub String);
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Package {
     pub id: PackageId,
     pub weight: Kilogram,
+    pub origin: NodeId,
     pub destination: NodeId,
     pub status: Status,
+    // Both counted from the start of the delivery run
+    #[serde(default)]
+    pub earliest_pickup: Option<Minute>,
+    #[serde(default)]
+    pub deadline: Option<Minute>,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default)]
+    pub volume: Option<Liter>,
 }
 
 impl Package {
@@ -33,17 +67,24 @@ impl Package {
         let status = if origin == destination {
             Status::Delivered(TrainId::default())
         } else {
-            Status::DroppedAt(origin, TrainId::default())
+            Status::DroppedAt(origin.clone(), TrainId::default())
         };
 
         Self {
             id: PackageId(name.into()),
             weight,
+            origin,
             destination,
             status,
+            ..Default::default()
         }
     }
 
+    /// Room the package takes in a train, none when not given.
+    pub fn get_volume(&self) -> Liter {
+        self.volume.clone().unwrap_or_default()
+    }
+
     pub fn is_package_loaded_in_train(&self, train_id: &TrainId) -> bool {
         match &self.status {
             Status::LoadedTo(train) => train == train_id,
@@ -58,26 +99,74 @@ impl Package {
         }
     }
 
-    pub fn drop_to_origin(&mut self) {
+    /// Station the package waits at, ready or not.
+    pub fn get_station(&self) -> Option<&NodeId> {
         match &self.status {
-            Status::CantBeTransported(node_id) => {
-                self.status = Status::DroppedAt(node_id.clone(), TrainId::default());
-            },
-            _ => {},
+            Status::DroppedAt(node_id, _)
+            | Status::CantBeTransported(node_id)
+            | Status::Unreachable(node_id, _)
+            | Status::NotReady(node_id) => Some(node_id),
+            _ => None,
+        }
+    }
+
+    pub fn is_delivered(&self) -> bool {
+        matches!(self.status, Status::Delivered(_) | Status::Completed)
+    }
+
+    /// Comes from, goes to or waits at the station.
+    pub fn uses_node(&self, node_id: &NodeId) -> bool {
+        self.origin == *node_id
+            || self.destination == *node_id
+            || self.get_station() == Some(node_id)
+    }
+
+    pub fn drop_to_origin(&mut self) {
+        if let Status::CantBeTransported(node_id) | Status::Unreachable(node_id, _) = &self.status {
+            self.status = Status::DroppedAt(node_id.clone(), TrainId::default());
         }
     }
 
     pub fn set_to_cant_be_transported(&mut self) {
-        match &self.status {
-            Status::DroppedAt(node_id, _) => {
-                self.status = Status::CantBeTransported(node_id.clone());
-            },
-            _ => {},
+        if let Status::DroppedAt(node_id, _) = &self.status {
+            self.status = Status::CantBeTransported(node_id.clone());
+        }
+    }
+
+    /// Earliest pickup time of a package that was never picked up at its
+    /// origin, `None` when it can be picked up right away.
+    pub fn get_ready_time(&self) -> Option<&Minute> {
+        let earliest_pickup = self.earliest_pickup.as_ref()?;
+        let is_waiting = match &self.status {
+            Status::NotReady(_) => true,
+            Status::DroppedAt(node_id, train_id) => {
+                *node_id == self.origin && *train_id == TrainId::default()
+            }
+            _ => false,
+        };
+        (is_waiting && *earliest_pickup > Minute(0)).then_some(earliest_pickup)
+    }
+
+    pub fn hold_until_ready(&mut self) -> Option<Minute> {
+        let ready_time = self.get_ready_time()?.clone();
+        self.status = Status::NotReady(self.origin.clone());
+        Some(ready_time)
+    }
+
+    pub fn set_to_ready(&mut self) {
+        if let Status::NotReady(node_id) = &self.status {
+            self.status = Status::DroppedAt(node_id.clone(), TrainId::default());
+        }
+    }
+
+    pub fn set_to_unreachable(&mut self, reason: UnreachableReason) {
+        if let Status::DroppedAt(node_id, _) = &self.status {
+            self.status = Status::Unreachable(node_id.clone(), reason);
         }
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct PackageHandler {
     pub packages: Vec<Package>,
 }
@@ -102,17 +191,20 @@ impl PackageHandler {
         destination: NodeId,
     ) -> Result<()> {
         if self.find_package_index_by_name(name).is_some() {
-            return Err(Error::new(
-                ErrorKind::AddPackageError,
-                format!("Package '{name}' already existed"),
-            )
-            .into());
+            return Err(Error::DuplicatePackage(PackageId(name.into())));
         }
         self.packages
             .push(Package::new(name, weight, origin, destination));
         Ok(())
     }
 
+    pub fn remove_package(&mut self, package_id: &PackageId) -> Result<Package> {
+        let pos = self
+            .find_package_index_by_id(package_id)
+            .ok_or_else(|| Error::UnknownPackage(package_id.clone()))?;
+        Ok(self.packages.remove(pos))
+    }
+
     pub fn have_undelivered_packages(&self) -> bool {
         self.packages.iter().any(|package| match &package.status {
             Status::Completed => false,
@@ -120,6 +212,8 @@ impl PackageHandler {
             Status::LoadedTo(_) => true,
             Status::Delivered(_) => false,
             Status::CantBeTransported(_) => false,
+            Status::Unreachable(_, _) => false,
+            Status::NotReady(_) => true,
         })
     }
 
@@ -156,6 +250,14 @@ impl PackageHandler {
             .collect()
     }
 
+    pub fn list_packages_in_train(&self, train_id: &TrainId) -> Vec<PackageId> {
+        self.packages
+            .iter()
+            .filter(|package| package.is_package_loaded_in_train(train_id))
+            .map(|package| package.id.clone())
+            .collect()
+    }
+
     pub fn list_package_names_delivered(&self, train_id: &TrainId) -> Vec<String> {
         self.packages
             .iter()
@@ -179,7 +281,6 @@ impl PackageHandler {
         self.packages
             .iter_mut()
             .filter(|package| matches!(&package.status, Status::DroppedAt(_, _)))
-            .map(|package| package)
             .collect()
     }
 
@@ -189,7 +290,13 @@ impl PackageHandler {
             .filter(|package| {
                 matches!(&package.status, Status::CantBeTransported(_))
             })
-            .map(|package| package)
+            .collect()
+    }
+
+    pub fn list_unreachable_packages_mut(&mut self) -> Vec<&mut Package> {
+        self.packages
+            .iter_mut()
+            .filter(|package| matches!(&package.status, Status::Unreachable(_, _)))
             .collect()
     }
 
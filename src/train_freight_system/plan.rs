@@ -0,0 +1,189 @@
+use std::path::Path;
+
+use crate::util::{datetime::DateTime, minute::Minute};
+
+use super::{
+    edge::EdgeId,
+    error::Result,
+    node::NodeId,
+    package::{Package, PackageId, Priority},
+    timeline,
+    train::TrainId,
+};
+
+/// Minutes of the run as `W=90`, followed by the clock time like
+/// `W=90 (2024-05-01 09:30)` when the run has a start.
+pub fn format_time(start: Option<&DateTime>, time: &Minute) -> String {
+    match start {
+        Some(start) => format!("W={} ({})", time.0, start.clone() + time.clone()),
+        None => format!("W={}", time.0),
+    }
+}
+
+/// Train going along an edge, with the packages it took from the origin and
+/// left at the destination.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainMove {
+    pub train: TrainId,
+    pub departure: Minute,
+    pub arrival: Minute,
+    pub origin: NodeId,
+    pub destination: NodeId,
+    pub edge: EdgeId,
+    pub picked_up: Vec<PackageId>,
+    pub dropped_off: Vec<PackageId>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageDelivery {
+    pub package: PackageId,
+    pub train: TrainId,
+    pub time: Minute,
+    // How long after its deadline the package got delivered, zero when on
+    // time and `None` when it has no deadline
+    pub lateness: Option<Minute>,
+    pub priority: Priority,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WaitReason {
+    // Held at the station until a track of the edge it leaves on is free
+    TrackTaken,
+    // Queued on the edge it arrives on until a platform of the station is free
+    StationFull,
+    // Held at the station until its earliest departure time
+    NotDeparted,
+}
+
+/// Train kept waiting at a station, or on an edge just before it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainWait {
+    pub train: TrainId,
+    pub station: NodeId,
+    pub edge: EdgeId,
+    pub from: Minute,
+    pub until: Minute,
+    pub reason: WaitReason,
+}
+
+/// Delivery times of the packages of one priority class.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriorityClassTimes {
+    pub priority: Priority,
+    pub delivered: usize,
+    pub average_time: Minute,
+    pub latest_time: Minute,
+}
+
+/// What `deliver_packages` did, moves in the order the trains left and
+/// deliveries in the order they happened.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DeliveryPlan {
+    pub total_time: Minute,
+    pub moves: Vec<TrainMove>,
+    pub deliveries: Vec<PackageDelivery>,
+    pub waits: Vec<TrainWait>,
+    // Clock time of minute zero, when the run was given one
+    pub start: Option<DateTime>,
+}
+
+impl DeliveryPlan {
+    /// Clock time of a time of the plan.
+    pub fn clock_time(&self, time: &Minute) -> Option<DateTime> {
+        self.start.clone().map(|start| start + time.clone())
+    }
+
+    pub fn delivery_time(&self, package_id: &PackageId) -> Option<&Minute> {
+        self.deliveries
+            .iter()
+            .find(|delivery| delivery.package == *package_id)
+            .map(|delivery| &delivery.time)
+    }
+
+    /// Packages that reached their destination at the end of the move.
+    pub fn list_packages_delivered_by(&self, train_move: &TrainMove) -> Vec<PackageId> {
+        self.deliveries
+            .iter()
+            .filter(|delivery| {
+                delivery.train == train_move.train
+                    && delivery.time == train_move.arrival
+                    && train_move.dropped_off.contains(&delivery.package)
+            })
+            .map(|delivery| delivery.package.clone())
+            .collect()
+    }
+
+    fn find_last_move_of_train_mut(&mut self, train_id: &TrainId) -> Option<&mut TrainMove> {
+        self.moves
+            .iter_mut()
+            .rev()
+            .find(|train_move| train_move.train == *train_id)
+    }
+
+    pub fn record_departure(&mut self, train_move: TrainMove) {
+        self.moves.push(train_move);
+    }
+
+    pub fn record_wait(&mut self, wait: TrainWait) {
+        self.waits.push(wait);
+    }
+
+    pub fn record_arrival(&mut self, train_id: &TrainId, packages: Vec<PackageId>) {
+        if let Some(train_move) = self.find_last_move_of_train_mut(train_id) {
+            train_move.dropped_off = packages;
+        }
+    }
+
+    pub fn record_delivery(&mut self, package: &Package, train_id: &TrainId, time: Minute) {
+        let lateness = package
+            .deadline
+            .as_ref()
+            .map(|deadline| Minute(time.0.saturating_sub(deadline.0)));
+        self.deliveries.push(PackageDelivery {
+            package: package.id.clone(),
+            train: train_id.clone(),
+            time,
+            lateness,
+            priority: package.priority,
+        });
+    }
+
+    pub fn list_late_deliveries(&self) -> Vec<&PackageDelivery> {
+        self.deliveries
+            .iter()
+            .filter(|delivery| delivery.lateness.as_ref().is_some_and(|late| late.0 > 0))
+            .collect()
+    }
+
+    /// Gantt-style timeline of the moves, see `timeline::render`.
+    pub fn to_svg(&self) -> String {
+        timeline::render(self)
+    }
+
+    pub fn save_svg(&self, path: impl AsRef<Path>) -> Result<()> {
+        timeline::write(self, path)
+    }
+
+    /// Classes with at least one delivery, highest first.
+    pub fn list_delivery_times_by_priority(&self) -> Vec<PriorityClassTimes> {
+        let mut classes: Vec<PriorityClassTimes> = vec![];
+        for priority in [Priority::Express, Priority::Standard, Priority::Economy] {
+            let times: Vec<u32> = self
+                .deliveries
+                .iter()
+                .filter(|delivery| delivery.priority == priority)
+                .map(|delivery| delivery.time.0)
+                .collect();
+            let Some(latest_time) = times.iter().max() else {
+                continue;
+            };
+            classes.push(PriorityClassTimes {
+                priority,
+                delivered: times.len(),
+                average_time: Minute(times.iter().sum::<u32>() / times.len() as u32),
+                latest_time: Minute(*latest_time),
+            });
+        }
+        classes
+    }
+}
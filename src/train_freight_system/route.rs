@@ -0,0 +1,341 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use crate::util::minute::Minute;
+
+use super::{
+    edge::EdgeId,
+    node::{Node, NodeId},
+};
+
+/// Least-time path between two stations, including both endpoints. The train
+/// moves from `nodes[i]` to `nodes[i + 1]` along `edges[i]`, so that parallel
+/// edges between the same stations are told apart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub nodes: Vec<NodeId>,
+    pub edges: Vec<EdgeId>,
+    pub travel_time: Minute,
+}
+
+impl Route {
+    /// Station the train has to move to first, `None` when origin and
+    /// destination are the same station.
+    pub fn next_hop(&self) -> Option<&NodeId> {
+        self.nodes.get(1)
+    }
+
+    /// Edge to take to the next hop.
+    pub fn next_edge(&self) -> Option<&EdgeId> {
+        self.edges.first()
+    }
+
+    pub fn contains(&self, node_id: &NodeId) -> bool {
+        self.nodes.contains(node_id)
+    }
+}
+
+// Position of the next station and the edge to take to it
+type Hop = (usize, EdgeId);
+
+/// All-pairs least travel times and next hops between stations, along with
+/// the edge leading to each hop, so that the planner can query them in O(1)
+/// instead of searching on every tick.
+#[derive(Debug, Default, Clone)]
+pub struct RouteTable {
+    node_ids: Vec<NodeId>,
+    positions: HashMap<NodeId, usize>,
+    travel_times: Vec<Vec<Option<Minute>>>,
+    next_hops: Vec<Vec<Option<Hop>>>,
+    components: Vec<usize>,
+}
+
+impl RouteTable {
+    pub fn new(nodes: &[Node]) -> Self {
+        let node_ids: Vec<NodeId> = nodes.iter().map(|node| node.id.clone()).collect();
+        let positions: HashMap<NodeId, usize> = node_ids
+            .iter()
+            .enumerate()
+            .map(|(index, node_id)| (node_id.clone(), index))
+            .collect();
+
+        let mut travel_times = Vec::with_capacity(nodes.len());
+        let mut next_hops = Vec::with_capacity(nodes.len());
+        for origin_pos in 0..nodes.len() {
+            let (times, hops) = find_shortest_paths(nodes, &positions, origin_pos);
+            travel_times.push(times);
+            next_hops.push(hops);
+        }
+
+        Self {
+            components: find_connected_components(nodes, &positions),
+            node_ids,
+            positions,
+            travel_times,
+            next_hops,
+        }
+    }
+
+    /// Connected part of the network the station belongs to, whatever the
+    /// direction of the edges, stations in the same one share the same number.
+    pub fn component(&self, node_id: &NodeId) -> Option<usize> {
+        let pos = *self.positions.get(node_id)?;
+        Some(self.components[pos])
+    }
+
+    pub fn is_connected(&self, node_a_id: &NodeId, node_b_id: &NodeId) -> bool {
+        match (self.component(node_a_id), self.component(node_b_id)) {
+            (Some(component_a), Some(component_b)) => component_a == component_b,
+            _ => false,
+        }
+    }
+
+    /// Unlike `is_connected`, follows one-way edges in their direction only.
+    pub fn can_reach(&self, origin: &NodeId, destination: &NodeId) -> bool {
+        self.travel_time(origin, destination).is_some()
+    }
+
+    pub fn travel_time(&self, origin: &NodeId, destination: &NodeId) -> Option<Minute> {
+        let origin_pos = *self.positions.get(origin)?;
+        let destination_pos = *self.positions.get(destination)?;
+        self.travel_times[origin_pos][destination_pos].clone()
+    }
+
+    /// Station to move to from `origin` in order to reach `destination` the
+    /// fastest. `None` when unreachable or already there.
+    pub fn next_hop(&self, origin: &NodeId, destination: &NodeId) -> Option<&NodeId> {
+        let origin_pos = *self.positions.get(origin)?;
+        let destination_pos = *self.positions.get(destination)?;
+        let (pos, _) = self.next_hops[origin_pos][destination_pos].as_ref()?;
+        Some(&self.node_ids[*pos])
+    }
+
+    /// Edge out of `origin` leading to `next_hop`, the fastest one when there
+    /// are parallel edges.
+    pub fn next_edge(&self, origin: &NodeId, destination: &NodeId) -> Option<&EdgeId> {
+        let origin_pos = *self.positions.get(origin)?;
+        let destination_pos = *self.positions.get(destination)?;
+        let (_, edge_id) = self.next_hops[origin_pos][destination_pos].as_ref()?;
+        Some(edge_id)
+    }
+
+    /// Returns `None` when the destination can't be reached from the origin.
+    pub fn route(&self, origin: &NodeId, destination: &NodeId) -> Option<Route> {
+        let origin_pos = *self.positions.get(origin)?;
+        let destination_pos = *self.positions.get(destination)?;
+        let travel_time = self.travel_times[origin_pos][destination_pos].clone()?;
+
+        let mut nodes = vec![self.node_ids[origin_pos].clone()];
+        let mut edges = vec![];
+        let mut pos = origin_pos;
+        while pos != destination_pos {
+            let (next_pos, edge_id) = self.next_hops[pos][destination_pos].as_ref()?;
+            pos = *next_pos;
+            nodes.push(self.node_ids[pos].clone());
+            edges.push(edge_id.clone());
+        }
+
+        Some(Route {
+            nodes,
+            edges,
+            travel_time,
+        })
+    }
+}
+
+/// Numbers the connected parts of the network by walking `Node::edges` both
+/// ways from each station not visited yet.
+fn find_connected_components(nodes: &[Node], positions: &HashMap<NodeId, usize>) -> Vec<usize> {
+    let mut neighbours: Vec<Vec<usize>> = vec![vec![]; nodes.len()];
+    for (pos, node) in nodes.iter().enumerate() {
+        for edge in &node.edges {
+            let Some(&next_pos) = positions.get(&edge.node) else {
+                continue;
+            };
+            neighbours[pos].push(next_pos);
+            neighbours[next_pos].push(pos);
+        }
+    }
+
+    let mut components: Vec<Option<usize>> = vec![None; nodes.len()];
+    let mut count = 0;
+    for start_pos in 0..nodes.len() {
+        if components[start_pos].is_some() {
+            continue;
+        }
+
+        components[start_pos] = Some(count);
+        let mut stack = vec![start_pos];
+        while let Some(pos) = stack.pop() {
+            for &next_pos in &neighbours[pos] {
+                if components[next_pos].is_none() {
+                    components[next_pos] = Some(count);
+                    stack.push(next_pos);
+                }
+            }
+        }
+        count += 1;
+    }
+
+    components.into_iter().flatten().collect()
+}
+
+/// Dijkstra over `Node::edges` weighted by `Edge::travel_time`, from a single
+/// origin to every station. Returns the least travel time to each station and
+/// the first station to move to, with the edge to take, in order to get there.
+fn find_shortest_paths(
+    nodes: &[Node],
+    positions: &HashMap<NodeId, usize>,
+    origin_pos: usize,
+) -> (Vec<Option<Minute>>, Vec<Option<Hop>>) {
+    let mut travel_times: Vec<Option<Minute>> = vec![None; nodes.len()];
+    let mut first_hops: Vec<Option<Hop>> = vec![None; nodes.len()];
+    let mut queue = BinaryHeap::new();
+
+    travel_times[origin_pos] = Some(Minute(0));
+    queue.push(Reverse((Minute(0), origin_pos)));
+
+    while let Some(Reverse((travel_time, pos))) = queue.pop() {
+        if travel_times[pos]
+            .as_ref()
+            .is_some_and(|best| *best < travel_time)
+        {
+            continue;
+        }
+
+        for edge in &nodes[pos].edges {
+            let Some(&next_pos) = positions.get(&edge.node) else {
+                continue;
+            };
+            let next_travel_time = travel_time.clone() + edge.travel_time.clone();
+            if travel_times[next_pos]
+                .as_ref()
+                .is_none_or(|best| next_travel_time < *best)
+            {
+                travel_times[next_pos] = Some(next_travel_time.clone());
+                first_hops[next_pos] = if pos == origin_pos {
+                    Some((next_pos, edge.id.clone()))
+                } else {
+                    first_hops[pos].clone()
+                };
+                queue.push(Reverse((next_travel_time, next_pos)));
+            }
+        }
+    }
+
+    (travel_times, first_hops)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::train_freight_system::edge::Edge;
+
+    fn network(edges: &[(&str, &str, &str, u32)], names: &[&str]) -> Vec<Node> {
+        let mut nodes: Vec<Node> = names.iter().map(|name| Node::new(name)).collect();
+        for (edge, node_1, node_2, time) in edges {
+            for node in nodes.iter_mut() {
+                if node.id.0 == *node_1 {
+                    node.add_edge(Edge::new(edge, NodeId(node_2.to_string()), Minute(*time)))
+                        .unwrap();
+                } else if node.id.0 == *node_2 {
+                    node.add_edge(Edge::new(edge, NodeId(node_1.to_string()), Minute(*time)))
+                        .unwrap();
+                }
+            }
+        }
+        nodes
+    }
+
+    #[test]
+    fn test_route_table() {
+        let nodes = network(
+            &[
+                ("E1", "A", "B", 30),
+                ("E2", "B", "C", 10),
+                ("E3", "A", "C", 50),
+                ("E4", "C", "D", 5),
+            ],
+            &["A", "B", "C", "D", "E"],
+        );
+        let table = RouteTable::new(&nodes);
+
+        let route = table
+            .route(&NodeId("A".into()), &NodeId("D".into()))
+            .expect("no route");
+        let names: Vec<&str> = route.nodes.iter().map(|id| id.0.as_str()).collect();
+        assert_eq!(names, vec!["A", "B", "C", "D"]);
+        let edges: Vec<&str> = route.edges.iter().map(|id| id.0.as_str()).collect();
+        assert_eq!(edges, vec!["E1", "E2", "E4"]);
+        assert_eq!(route.travel_time, Minute(45));
+        assert_eq!(route.next_hop(), Some(&NodeId("B".into())));
+        assert_eq!(route.next_edge(), Some(&EdgeId("E1".into())));
+        assert_eq!(
+            table.next_hop(&NodeId("D".into()), &NodeId("A".into())),
+            Some(&NodeId("C".into()))
+        );
+        assert_eq!(
+            table.travel_time(&NodeId("D".into()), &NodeId("A".into())),
+            Some(Minute(45))
+        );
+
+        let route = table
+            .route(&NodeId("C".into()), &NodeId("C".into()))
+            .expect("no route");
+        assert_eq!(route.nodes, vec![NodeId("C".into())]);
+        assert_eq!(route.travel_time, Minute(0));
+
+        // Disconnected station
+        assert!(table
+            .route(&NodeId("A".into()), &NodeId("E".into()))
+            .is_none());
+        assert!(table
+            .next_hop(&NodeId("A".into()), &NodeId("E".into()))
+            .is_none());
+        // Unknown station
+        assert!(table
+            .route(&NodeId("A".into()), &NodeId("Z".into()))
+            .is_none());
+
+        assert!(table.is_connected(&NodeId("D".into()), &NodeId("A".into())));
+        assert!(!table.is_connected(&NodeId("A".into()), &NodeId("E".into())));
+        assert!(!table.is_connected(&NodeId("A".into()), &NodeId("Z".into())));
+        assert_ne!(
+            table.component(&NodeId("A".into())),
+            table.component(&NodeId("E".into()))
+        );
+    }
+
+    #[test]
+    fn test_directed_route_table() {
+        let mut nodes: Vec<Node> = ["A", "B", "C", "D"]
+            .iter()
+            .map(|name| Node::new(name))
+            .collect();
+        for (edge, origin, destination, time) in [
+            ("E1", 0, "B", 10),
+            ("E2", 1, "C", 10),
+            ("E3", 2, "A", 10),
+            ("E4", 3, "A", 5),
+        ] {
+            nodes[origin]
+                .add_edge(Edge::new(edge, NodeId(destination.into()), Minute(time)))
+                .unwrap();
+        }
+        let table = RouteTable::new(&nodes);
+
+        // C to B goes round through A
+        let route = table
+            .route(&NodeId("C".into()), &NodeId("B".into()))
+            .expect("no route");
+        let names: Vec<&str> = route.nodes.iter().map(|id| id.0.as_str()).collect();
+        assert_eq!(names, vec!["C", "A", "B"]);
+        assert_eq!(route.travel_time, Minute(20));
+
+        assert!(table.can_reach(&NodeId("D".into()), &NodeId("C".into())));
+        assert!(!table.can_reach(&NodeId("A".into()), &NodeId("D".into())));
+        assert!(table.is_connected(&NodeId("A".into()), &NodeId("D".into())));
+    }
+}
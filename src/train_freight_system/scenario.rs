@@ -0,0 +1,233 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::util::{datetime::DateTime, minute::Minute};
+
+use super::{
+    edge::EdgeId,
+    error::Result,
+    node::{HandlingTime, NodeId},
+    package::Package,
+    train::Train,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EdgeScenario {
+    pub id: EdgeId,
+    pub node_1: NodeId,
+    pub node_2: NodeId,
+    pub travel_time: Minute,
+    #[serde(default)]
+    pub tracks: Option<u32>,
+    // Only from node_1 to node_2
+    #[serde(default)]
+    pub directed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HandlingTimeScenario {
+    pub node: NodeId,
+    pub handling_time: HandlingTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlatformsScenario {
+    pub node: NodeId,
+    pub platforms: u32,
+}
+
+/// Whole network state as saved to and loaded from JSON, trains and packages
+/// included with their status so that a run can be resumed.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    pub nodes: Vec<NodeId>,
+    pub edges: Vec<EdgeScenario>,
+    pub trains: Vec<Train>,
+    pub packages: Vec<Package>,
+    // Only the stations that take time to load or unload
+    #[serde(default)]
+    pub handling_times: Vec<HandlingTimeScenario>,
+    // Only the stations with a limited number of platforms
+    #[serde(default)]
+    pub platforms: Vec<PlatformsScenario>,
+    #[serde(default)]
+    pub start: Option<DateTime>,
+}
+
+impl Scenario {
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        train_freight_system::{
+            dispatch::DefaultDispatch,
+            error::Error,
+            package::{self, PackageId},
+            train::{self, TrainId},
+            TrainFreightSystem,
+        },
+        util::kilogram::Kilogram,
+    };
+
+    fn mid_run_system() -> TrainFreightSystem {
+        let mut system = TrainFreightSystem::default();
+        system.add_node("A").expect("can't add node");
+        system.add_node("B").expect("can't add node");
+        system.add_node("C").expect("can't add node");
+        system
+            .add_edge("E1", "A", "B", Minute(30))
+            .expect("can't add edge");
+        system.set_edge_tracks("E1", 1).expect("can't set tracks");
+        system.set_node_platforms("C", 1).expect("can't set platforms");
+        system
+            .add_edge("E2", "B", "C", Minute(10))
+            .expect("can't add edge");
+        system
+            .add_train("Q1", Kilogram(6), "B")
+            .expect("Can't add train");
+        system
+            .add_train("Q2", Kilogram(10), "A")
+            .expect("Can't add train");
+        system
+            .add_package("K1", Kilogram(5), "A", "C")
+            .expect("Can't add package");
+        system
+            .add_package("K2", Kilogram(4), "B", "A")
+            .expect("Can't add package");
+        system
+            .add_package("K3", Kilogram(50), "C", "A")
+            .expect("Can't add package");
+        system
+            .set_node_handling_time(
+                "B",
+                HandlingTime {
+                    per_stop: Minute(5),
+                    ..Default::default()
+                },
+            )
+            .expect("can't set handling time");
+        system.set_start("2024-05-01 08:00".parse().ok());
+        system.blacklist_packages_that_cant_be_transported();
+
+        let package = system
+            .package_handler
+            .get_package_mut(&PackageId("K1".into()))
+            .unwrap();
+        system
+            .train_handler
+            .load_package(&TrainId("Q2".into()), package);
+        system.train_handler.move_to_node(
+            &TrainId("Q2".into()),
+            &NodeId("A".into()),
+            &NodeId("B".into()),
+            &EdgeId("E1".into()),
+            Minute(20),
+        );
+        system
+    }
+
+    #[test]
+    fn test_scenario_round_trip() {
+        let system = mid_run_system();
+        let scenario = system.to_scenario();
+        assert_eq!(scenario.edges.len(), 2);
+        assert_eq!(scenario.handling_times.len(), 1);
+        assert_eq!(scenario.platforms.len(), 1);
+        assert_eq!(scenario.start, DateTime::new(2024, 5, 1, 8, 0));
+        let statuses: Vec<&package::Status> = scenario
+            .packages
+            .iter()
+            .map(|package| &package.status)
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                &package::Status::LoadedTo(TrainId("Q2".into())),
+                &package::Status::DroppedAt(NodeId("B".into()), TrainId::default()),
+                &package::Status::CantBeTransported(NodeId("C".into())),
+            ]
+        );
+
+        let json = scenario.to_json().expect("can't save scenario");
+        let loaded = Scenario::from_json(&json).expect("can't load scenario");
+        assert_eq!(loaded, scenario);
+
+        let path = std::env::temp_dir().join("train_freight_system_scenario.json");
+        system.save_scenario(&path).expect("can't save scenario");
+        let mut resumed = TrainFreightSystem::load_scenario(&path).expect("can't load scenario");
+        std::fs::remove_file(&path).expect("can't remove scenario");
+        assert_eq!(resumed.to_scenario(), scenario);
+        assert_eq!(
+            resumed.train_handler.trains[1].status,
+            train::Status::DeliveringTo(
+                NodeId("A".into()),
+                NodeId("B".into()),
+                EdgeId("E1".into()),
+                Minute(20)
+            )
+        );
+        assert!(resumed.deliver_packages(&mut DefaultDispatch).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_scenario() {
+        let mut scenario = mid_run_system().to_scenario();
+        scenario.packages[1].status = package::Status::LoadedTo(TrainId("Q9".into()));
+        assert!(matches!(
+            TrainFreightSystem::from_scenario(scenario),
+            Err(Error::UnknownTrain(train)) if train.0 == "Q9"
+        ));
+
+        let mut scenario = mid_run_system().to_scenario();
+        scenario.nodes.pop();
+        assert!(matches!(
+            TrainFreightSystem::from_scenario(scenario),
+            Err(Error::UnknownNode(node)) if node.0 == "C"
+        ));
+
+        // On an edge that doesn't go between its stations
+        let mut scenario = mid_run_system().to_scenario();
+        scenario.trains[1].status = train::Status::DeliveringTo(
+            NodeId("A".into()),
+            NodeId("B".into()),
+            EdgeId("E2".into()),
+            Minute(20),
+        );
+        assert!(matches!(
+            TrainFreightSystem::from_scenario(scenario),
+            Err(Error::NoEdge { from, edge }) if from.0 == "A" && edge.0 == "E2"
+        ));
+
+        let mut scenario = mid_run_system().to_scenario();
+        let train = scenario.trains[0].clone();
+        scenario.trains.push(train);
+        assert!(matches!(
+            TrainFreightSystem::from_scenario(scenario),
+            Err(Error::DuplicateTrain(train)) if train.0 == "Q1"
+        ));
+
+        assert!(matches!(
+            Scenario::from_json("{\"nodes\": [\"A\"]}"),
+            Err(Error::Json(_))
+        ));
+    }
+}
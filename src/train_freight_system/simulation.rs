@@ -0,0 +1,310 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::util::minute::Minute;
+
+use super::{
+    edge::EdgeId, node::NodeId, package::PackageId, plan::DeliveryPlan, train::TrainId,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    // Train(TrainId) leaves station(NodeId) along the edge(EdgeId)
+    Departure(TrainId, NodeId, EdgeId),
+    // Train(TrainId) stops at station(NodeId)
+    Arrival(TrainId, NodeId),
+    // Package(PackageId) is loaded to the train(TrainId)
+    Load(TrainId, PackageId),
+    // Package(PackageId) is taken off the train(TrainId) at its station
+    Unload(TrainId, PackageId),
+    // Package(PackageId) can be picked up from now on
+    PackageReady(PackageId),
+    // A train left a platform of station(NodeId)
+    PlatformFreed(NodeId),
+}
+
+#[derive(Debug)]
+struct ScheduledEvent {
+    time: Minute,
+    // Events due at the same time happen in the order they were scheduled
+    sequence: u64,
+    event: Event,
+}
+
+impl PartialEq for ScheduledEvent {
+    fn eq(&self, other: &Self) -> bool {
+        (&self.time, self.sequence) == (&other.time, other.sequence)
+    }
+}
+
+impl Eq for ScheduledEvent {}
+
+impl PartialOrd for ScheduledEvent {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScheduledEvent {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (&self.time, self.sequence).cmp(&(&other.time, other.sequence))
+    }
+}
+
+// Track of an edge between two stations, given in either order, taken by a
+// train from..until
+#[derive(Debug)]
+struct TrackReservation {
+    edge: EdgeId,
+    stations: (NodeId, NodeId),
+    from: Minute,
+    until: Minute,
+}
+
+impl TrackReservation {
+    fn is_on(&self, edge: &EdgeId, stations: &(NodeId, NodeId)) -> bool {
+        self.edge == *edge
+            && (self.stations == *stations
+                || (&self.stations.1, &self.stations.0) == (&stations.0, &stations.1))
+    }
+}
+
+/// Earliest time from `earliest` a train can be on a track for `travel_time`
+/// with less than `tracks` of the `reserved` from..until taken at any point.
+pub(crate) fn find_free_track(
+    reserved: &[(Minute, Minute)],
+    tracks: u32,
+    earliest: Minute,
+    travel_time: Minute,
+) -> Minute {
+    // Leaving right away or as a train gets off the track
+    let mut candidates: Vec<Minute> = reserved
+        .iter()
+        .map(|(_, until)| until.clone())
+        .filter(|until| *until > earliest)
+        .collect();
+    candidates.push(earliest);
+    candidates.sort();
+
+    // The trains on the track only change as one gets on it
+    candidates
+        .into_iter()
+        .find(|start| {
+            let end = start.clone() + travel_time.clone();
+            let mut times: Vec<&Minute> = reserved
+                .iter()
+                .map(|(from, _)| from)
+                .filter(|from| *from > start && **from < end)
+                .collect();
+            times.push(start);
+            times.into_iter().all(|time| {
+                let taken = reserved
+                    .iter()
+                    .filter(|(from, until)| from <= time && time < until)
+                    .count();
+                (taken as u32) < tracks
+            })
+        })
+        // Free at the latest once the last train got off
+        .unwrap()
+}
+
+/// Clock of a delivery run, the events waiting to happen, earliest first, and
+/// the plan of the ones that happened.
+#[derive(Debug, Default)]
+pub struct Simulation {
+    clock: Minute,
+    events: BinaryHeap<Reverse<ScheduledEvent>>,
+    scheduled: u64,
+    reservations: Vec<TrackReservation>,
+    // Stations of the trains that left but are still held at a platform
+    leaving: Vec<NodeId>,
+    // Train(TrainId) queued on the edge(EdgeId) for a platform at
+    // station(NodeId) since time(Minute), first come first served
+    queued: Vec<(TrainId, EdgeId, NodeId, Minute)>,
+    pub plan: DeliveryPlan,
+}
+
+impl Simulation {
+    pub fn now(&self) -> Minute {
+        self.clock.clone()
+    }
+
+    pub fn schedule(&mut self, time: Minute, event: Event) {
+        self.events.push(Reverse(ScheduledEvent {
+            time,
+            sequence: self.scheduled,
+            event,
+        }));
+        self.scheduled += 1;
+    }
+
+    pub fn schedule_now(&mut self, event: Event) {
+        self.schedule(self.now(), event);
+    }
+
+    /// Moves the clock to the next event, `None` when nothing is left to
+    /// happen.
+    pub fn advance(&mut self) -> Option<Minute> {
+        let Reverse(next) = self.events.peek()?;
+        if next.time > self.clock {
+            self.clock = next.time.clone();
+        }
+        Some(self.now())
+    }
+
+    /// Takes a track of the edge between the two `stations` for
+    /// `travel_time` at the earliest from `earliest`, and returns when the
+    /// train can leave. Edges without a track count are never full.
+    pub fn reserve_track(
+        &mut self,
+        edge: &EdgeId,
+        stations: (NodeId, NodeId),
+        tracks: Option<u32>,
+        earliest: Minute,
+        travel_time: Minute,
+    ) -> Minute {
+        let departure = match tracks {
+            None => earliest,
+            Some(tracks) => {
+                let reserved: Vec<(Minute, Minute)> = self
+                    .reservations
+                    .iter()
+                    .filter(|reservation| reservation.is_on(edge, &stations))
+                    .map(|reservation| (reservation.from.clone(), reservation.until.clone()))
+                    .collect();
+                find_free_track(&reserved, tracks, earliest, travel_time.clone())
+            }
+        };
+
+        self.reservations.push(TrackReservation {
+            edge: edge.clone(),
+            stations,
+            from: departure.clone(),
+            until: departure.clone() + travel_time,
+        });
+        departure
+    }
+
+    /// Keeps a platform of `station` taken until the train leaves at
+    /// `departure`.
+    pub fn leave_platform(&mut self, station: NodeId, departure: Minute) {
+        self.leaving.push(station.clone());
+        self.schedule(departure, Event::PlatformFreed(station));
+    }
+
+    pub fn free_platform(&mut self, station: &NodeId) {
+        if let Some(pos) = self.leaving.iter().position(|leaving| leaving == station) {
+            self.leaving.remove(pos);
+        }
+    }
+
+    pub fn count_leaving(&self, station: &NodeId) -> usize {
+        self.leaving
+            .iter()
+            .filter(|leaving| *leaving == station)
+            .count()
+    }
+
+    pub fn queue_train(&mut self, train_id: TrainId, edge: EdgeId, station: NodeId) {
+        let now = self.now();
+        self.queued.push((train_id, edge, station, now));
+    }
+
+    pub fn has_queued_trains(&self) -> bool {
+        !self.queued.is_empty()
+    }
+
+    /// Events waiting to happen, in no particular order.
+    pub fn list_events(&self) -> impl Iterator<Item = &Event> {
+        self.events
+            .iter()
+            .map(|Reverse(scheduled)| &scheduled.event)
+    }
+
+    /// First train queued for a platform at `station`, with the edge it
+    /// comes on and since when it waits.
+    pub fn take_queued_train(&mut self, station: &NodeId) -> Option<(TrainId, EdgeId, Minute)> {
+        let pos = self
+            .queued
+            .iter()
+            .position(|(_, _, queued_for, _)| queued_for == station)?;
+        let (train_id, edge, _, since) = self.queued.remove(pos);
+        Some((train_id, edge, since))
+    }
+
+    /// Takes the next event that is due by now.
+    pub fn next_due_event(&mut self) -> Option<Event> {
+        let Reverse(next) = self.events.peek()?;
+        if next.time > self.clock {
+            return None;
+        }
+        self.events.pop().map(|Reverse(scheduled)| scheduled.event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simulation() {
+        let train = TrainId("Q1".into());
+        let mut simulation = Simulation::default();
+        simulation.schedule(
+            Minute(30),
+            Event::Arrival(train.clone(), NodeId("B".into())),
+        );
+        simulation.schedule(
+            Minute(10),
+            Event::Arrival(train.clone(), NodeId("A".into())),
+        );
+        simulation.schedule(Minute(10), Event::PackageReady(PackageId("K1".into())));
+        assert_eq!(simulation.next_due_event(), None);
+
+        assert_eq!(simulation.advance(), Some(Minute(10)));
+        assert_eq!(
+            simulation.next_due_event(),
+            Some(Event::Arrival(train.clone(), NodeId("A".into())))
+        );
+        simulation.schedule_now(Event::Unload(train.clone(), PackageId("K2".into())));
+        assert_eq!(
+            simulation.next_due_event(),
+            Some(Event::PackageReady(PackageId("K1".into())))
+        );
+        assert_eq!(
+            simulation.next_due_event(),
+            Some(Event::Unload(train.clone(), PackageId("K2".into())))
+        );
+        assert_eq!(simulation.next_due_event(), None);
+
+        assert_eq!(simulation.advance(), Some(Minute(30)));
+        assert_eq!(
+            simulation.next_due_event(),
+            Some(Event::Arrival(train, NodeId("B".into())))
+        );
+        assert_eq!(simulation.advance(), None);
+        assert_eq!(simulation.now(), Minute(30));
+    }
+
+    #[test]
+    fn test_reserve_track() {
+        let mut simulation = Simulation::default();
+        let edge = EdgeId("E1".into());
+        let a_to_b = (NodeId("A".into()), NodeId("B".into()));
+        let b_to_a = (NodeId("B".into()), NodeId("A".into()));
+        let mut reserve = |stations: &(NodeId, NodeId), tracks, earliest| {
+            simulation.reserve_track(&edge, stations.clone(), tracks, Minute(earliest), Minute(30))
+        };
+        assert_eq!(reserve(&a_to_b, Some(1), 10), Minute(10));
+        // Either direction takes the same track
+        assert_eq!(reserve(&b_to_a, Some(1), 0), Minute(40));
+        // Fits in before the first train gets on the edge
+        assert_eq!(reserve(&a_to_b, Some(2), 0), Minute(0));
+        assert_eq!(reserve(&b_to_a, None, 20), Minute(20));
+
+        let reserved = [(Minute(0), Minute(30)), (Minute(40), Minute(70))];
+        assert_eq!(find_free_track(&reserved, 1, Minute(0), Minute(10)), Minute(30));
+        assert_eq!(find_free_track(&reserved, 1, Minute(0), Minute(20)), Minute(70));
+        assert_eq!(find_free_track(&reserved, 2, Minute(0), Minute(20)), Minute(0));
+    }
+}
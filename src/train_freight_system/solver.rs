@@ -0,0 +1,1211 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use crate::util::{kilogram::Kilogram, minute::Minute};
+
+use super::{
+    dispatch::{DefaultDispatch, DispatchStrategy, TrainAction},
+    error::{Error, Result},
+    edge::EdgeId,
+    node::{HandlingTime, NodeId},
+    simulation::find_free_track,
+    package::{self, PackageId},
+    train::{self, TrainId},
+    TrainFreightSystem,
+};
+
+pub const MAX_TRAINS: usize = 6;
+pub const MAX_PACKAGES: usize = 12;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedMove {
+    pub departure: Minute,
+    pub train: TrainId,
+    pub origin: NodeId,
+    pub destination: NodeId,
+    pub edge: EdgeId,
+    pub packages: Vec<PackageId>,
+}
+
+/// Shortest plan found by `solve`. It is exact only for trains dropping their
+/// packages at every stop, a plan keeping packages on board can be shorter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptimalPlan {
+    pub total_time: Minute,
+    pub moves: Vec<PlannedMove>,
+}
+
+/// Finds the plan that delivers every undelivered package the earliest
+/// within the model `deliver_packages` runs on, using best-first branch and
+/// bound over the train moves and the packages they carry.
+///
+/// Trains decide at time zero and whenever a train arrives, and drop their
+/// packages at every stop like `deliver_packages` does, so packages can be
+/// handed over between trains but never stay on board past a stop. Trains
+/// and packages back where they already were at an earlier time are not
+/// searched again. The plan is the shortest of that model, not the exact
+/// minimum makespan when keeping packages on board would be faster. When
+/// `deliver_packages` with `DefaultDispatch` delivers everything, only plans
+/// that beat it are searched. The search is exponential and meant for small
+/// yards, at most `MAX_TRAINS` trains and `MAX_PACKAGES` packages. Scheduled
+/// trains and the packages on them are left out, only the trains dispatched
+/// on demand are planned.
+pub fn solve(system: &TrainFreightSystem) -> Result<OptimalPlan> {
+    let mut search = Search::new(system)?;
+    let state = search.initial_state(system);
+    if let Some(plan) = search.search(state.clone()) {
+        return Ok(plan);
+    }
+    if search.bound == u32::MAX {
+        return Err(Error::NoPlan);
+    }
+
+    // Trains of the heuristic can take a single track in an order the search
+    // doesn't try, so its makespan is no bound then
+    search.bound = u32::MAX;
+    search.seen.clear();
+    search.search(state).ok_or(Error::NoPlan)
+}
+
+// Trains the search moves around
+fn is_planned(train: &train::Train) -> bool {
+    train.status != train::Status::NotAvailable && !train.is_scheduled()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum TrainState {
+    Idle(usize),
+    // Moving to station(usize), arriving at time(u32)
+    Moving(usize, u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum PackageState {
+    At(usize),
+    On(usize),
+    Delivered,
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    time: u32,
+    trains: Vec<TrainState>,
+    packages: Vec<PackageState>,
+    // Idle trains that decided to wait for the next arrival
+    waiting: u64,
+    // Limited track(usize) each train is taken from..until
+    on_track: Vec<Option<(usize, u32, u32)>>,
+}
+
+type Key = (Vec<TrainState>, Vec<PackageState>, u64, Vec<Option<(usize, u32, u32)>>);
+
+impl State {
+    fn is_completed(&self) -> bool {
+        self.packages
+            .iter()
+            .all(|package| *package == PackageState::Delivered)
+    }
+
+    // Same trains and packages at a later time can't do better, so the time is
+    // kept out of the key and arrivals are made relative to it. Packages that
+    // became ready in between make it a different state.
+    fn key(&self, ready_times: &[u32]) -> Key {
+        let trains = self
+            .trains
+            .iter()
+            .map(|train| match train {
+                TrainState::Idle(node) => TrainState::Idle(*node),
+                TrainState::Moving(node, arrival) => TrainState::Moving(*node, arrival - self.time),
+            })
+            .collect();
+        let not_ready = ready_times
+            .iter()
+            .enumerate()
+            .filter(|(_, ready_time)| **ready_time > self.time)
+            .fold(0u64, |mask, (package, _)| mask | 1 << package);
+        let on_track = self
+            .on_track
+            .iter()
+            .map(|taken| {
+                taken.map(|(track, from, until)| {
+                    (track, from.saturating_sub(self.time), until.saturating_sub(self.time))
+                })
+            })
+            .collect();
+        (
+            trains,
+            self.packages.clone(),
+            self.waiting | not_ready << MAX_TRAINS,
+            on_track,
+        )
+    }
+}
+
+struct Search {
+    node_ids: Vec<NodeId>,
+    // Next station, travel time and track of the edges of each station
+    edges: Vec<Vec<(usize, u32, usize)>>,
+    // Id of the edges of each station, in the same order
+    edge_ids: Vec<Vec<EdgeId>>,
+    tracks: Vec<Option<u32>>,
+    platforms: Vec<Option<u32>>,
+    distances: Vec<Vec<Option<u32>>>,
+    handling_times: Vec<HandlingTime>,
+    train_ids: Vec<TrainId>,
+    capacities: Vec<u32>,
+    departure_times: Vec<u32>,
+    // Unlimited when the train has no volume limit
+    volume_capacities: Vec<u32>,
+    package_ids: Vec<PackageId>,
+    weights: Vec<u32>,
+    volumes: Vec<u32>,
+    destinations: Vec<usize>,
+    ready_times: Vec<u32>,
+    seen: HashMap<Key, u32>,
+    bound: u32,
+}
+
+impl Search {
+    fn new(system: &TrainFreightSystem) -> Result<Self> {
+        let node_ids: Vec<NodeId> = system.nodes.iter().map(|node| node.id.clone()).collect();
+        let position = |node_id: &NodeId| node_ids.iter().position(|id| id == node_id).unwrap();
+        // An edge is on both of its stations, both sides share its tracks
+        let mut edges: Vec<Vec<(usize, u32, usize)>> = vec![];
+        let mut edge_ids = vec![];
+        let mut track_ids: Vec<(EdgeId, usize, usize)> = vec![];
+        let mut tracks = vec![];
+        for (pos, node) in system.nodes.iter().enumerate() {
+            let mut node_edges = vec![];
+            for edge in &node.edges {
+                let next = position(&edge.node);
+                let track_id = (edge.id.clone(), pos.min(next), pos.max(next));
+                let track = match track_ids.iter().position(|id| *id == track_id) {
+                    Some(track) => track,
+                    None => {
+                        track_ids.push(track_id);
+                        tracks.push(edge.tracks);
+                        tracks.len() - 1
+                    }
+                };
+                node_edges.push((next, edge.travel_time.0, track));
+            }
+            edges.push(node_edges);
+            edge_ids.push(node.edges.iter().map(|edge| edge.id.clone()).collect());
+        }
+        let route_table = system.route_table();
+        let distances = node_ids
+            .iter()
+            .map(|origin| {
+                node_ids
+                    .iter()
+                    .map(|destination| {
+                        route_table
+                            .travel_time(origin, destination)
+                            .map(|time| time.0)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // Trains that are not available never leave and scheduled ones keep
+        // to their timetable, the search goes without them
+        let trains: Vec<_> = system
+            .train_handler
+            .trains
+            .iter()
+            .filter(|train| is_planned(train))
+            .collect();
+        let packages: Vec<_> = system
+            .package_handler
+            .packages
+            .iter()
+            .filter(|package| match &package.status {
+                package::Status::DroppedAt(_, _) | package::Status::NotReady(_) => true,
+                package::Status::LoadedTo(train_id) => {
+                    trains.iter().any(|train| train.id == *train_id)
+                }
+                _ => false,
+            })
+            .collect();
+        if trains.len() > MAX_TRAINS || packages.len() > MAX_PACKAGES {
+            return Err(Error::InstanceTooLarge {
+                trains: trains.len(),
+                packages: packages.len(),
+            });
+        }
+
+        let mut search = Self {
+            edges,
+            edge_ids,
+            tracks,
+            platforms: system.nodes.iter().map(|node| node.platforms).collect(),
+            distances,
+            handling_times: system
+                .nodes
+                .iter()
+                .map(|node| node.handling_time.clone())
+                .collect(),
+            train_ids: trains.iter().map(|train| train.id.clone()).collect(),
+            capacities: trains.iter().map(|train| train.max_capacity.0).collect(),
+            departure_times: trains
+                .iter()
+                .map(|train| train.earliest_departure.as_ref().map_or(0, |time| time.0))
+                .collect(),
+            volume_capacities: trains
+                .iter()
+                .map(|train| {
+                    train
+                        .max_volume
+                        .as_ref()
+                        .map_or(u32::MAX, |volume| volume.0)
+                })
+                .collect(),
+            package_ids: packages.iter().map(|package| package.id.clone()).collect(),
+            weights: packages.iter().map(|package| package.weight.0).collect(),
+            volumes: packages
+                .iter()
+                .map(|package| package.get_volume().0)
+                .collect(),
+            destinations: packages
+                .iter()
+                .map(|package| position(&package.destination))
+                .collect(),
+            ready_times: packages
+                .iter()
+                .map(|package| package.get_ready_time().map_or(0, |time| time.0))
+                .collect(),
+            node_ids,
+            seen: HashMap::new(),
+            bound: 0,
+        };
+        search.bound = search.upper_bound(system)?;
+        Ok(search)
+    }
+
+    fn position(&self, node_id: &NodeId) -> usize {
+        self.node_ids.iter().position(|id| id == node_id).unwrap()
+    }
+
+    // Fits in the empty train, by weight and volume
+    fn can_carry(&self, train: usize, package: usize) -> bool {
+        self.capacities[train] >= self.weights[package]
+            && self.volume_capacities[train] >= self.volumes[package]
+    }
+
+    fn get_track_taken(
+        &self,
+        origin: &NodeId,
+        edge_id: &EdgeId,
+        arrival: u32,
+    ) -> Option<(usize, u32, u32)> {
+        let origin = self.position(origin);
+        let pos = self.edge_ids[origin].iter().position(|id| id == edge_id)?;
+        let (_, travel_time, track) = self.edges[origin][pos];
+        self.tracks[track]?;
+        Some((track, arrival.saturating_sub(travel_time), arrival))
+    }
+
+    fn initial_state(&self, system: &TrainFreightSystem) -> State {
+        let trains: Vec<TrainState> = system
+            .train_handler
+            .trains
+            .iter()
+            .filter(|train| is_planned(train))
+            .filter_map(|train| match &train.status {
+                train::Status::DeliveringTo(_, destination, _, travel_time) => Some(
+                    TrainState::Moving(self.position(destination), travel_time.0),
+                ),
+                // Arrives again as soon as the run starts
+                train::Status::QueuedFor(_, destination, _) => {
+                    Some(TrainState::Moving(self.position(destination), 0))
+                }
+                train::Status::StoppedAt(node_id) => Some(TrainState::Idle(self.position(node_id))),
+                train::Status::NotAvailable => None,
+            })
+            .collect();
+        let packages = self
+            .package_ids
+            .iter()
+            .map(|package_id| {
+                let package = system.package_handler.get_package(package_id).unwrap();
+                match &package.status {
+                    package::Status::LoadedTo(train_id) => PackageState::On(
+                        self.train_ids.iter().position(|id| id == train_id).unwrap(),
+                    ),
+                    package::Status::NotReady(node_id) => PackageState::At(self.position(node_id)),
+                    _ => PackageState::At(self.position(package.get_location().unwrap())),
+                }
+            })
+            .collect();
+
+        // Moving trains are on their edge until they get off it, as in
+        // `deliver_packages`
+        let on_track = system
+            .train_handler
+            .trains
+            .iter()
+            .filter(|train| is_planned(train))
+            .filter_map(|train| match &train.status {
+                train::Status::DeliveringTo(origin, _, edge_id, arrival) => {
+                    Some(self.get_track_taken(origin, edge_id, arrival.0))
+                }
+                train::Status::StoppedAt(_) | train::Status::QueuedFor(_, _, _) => Some(None),
+                train::Status::NotAvailable => None,
+            })
+            .collect();
+
+        State {
+            time: 0,
+            trains,
+            packages,
+            waiting: 0,
+            on_track,
+        }
+    }
+
+    // Makespan of the heuristic plan, any better plan has to beat it. The
+    // packages that no train can deliver are reported first, the heuristic
+    // would leave them behind.
+    fn upper_bound(&self, system: &TrainFreightSystem) -> Result<u32> {
+        let state = self.initial_state(system);
+        if self.capacities.is_empty() {
+            return if state.is_completed() {
+                Ok(0)
+            } else {
+                Err(Error::NoTrains)
+            };
+        }
+
+        let location = |train: usize| match state.trains[train] {
+            TrainState::Idle(node) | TrainState::Moving(node, _) => node,
+        };
+        for (package, package_state) in state.packages.iter().enumerate() {
+            let origin = match package_state {
+                PackageState::At(node) => *node,
+                PackageState::On(train) => location(*train),
+                PackageState::Delivered => continue,
+            };
+            let carriers: Vec<usize> = (0..self.capacities.len())
+                .filter(|&train| self.can_carry(train, package))
+                .collect();
+            if carriers.is_empty() {
+                return Err(Error::NoTrainFits(self.package_ids[package].clone()));
+            }
+            let picked_up = matches!(package_state, PackageState::On(_))
+                || carriers
+                    .iter()
+                    .any(|&train| self.distances[location(train)][origin].is_some());
+            if !picked_up || self.distances[origin][self.destinations[package]].is_none() {
+                return Err(Error::Unreachable {
+                    package: self.package_ids[package].clone(),
+                    from: self.node_ids[origin].clone(),
+                    to: self.node_ids[self.destinations[package]].clone(),
+                });
+            }
+        }
+
+        // The heuristic has the scheduled trains the search goes without, so
+        // its makespan is no bound then
+        if system
+            .train_handler
+            .trains
+            .iter()
+            .any(train::Train::is_scheduled)
+        {
+            return Ok(u32::MAX);
+        }
+        let mut heuristic = system.clone();
+        let plan = heuristic.deliver_packages(&mut LoopGuard::default());
+        let delivered = self.package_ids.iter().all(|package_id| {
+            heuristic
+                .package_handler
+                .get_package(package_id)
+                .is_some_and(|package| {
+                    matches!(
+                        package.status,
+                        package::Status::Delivered(_) | package::Status::Completed
+                    )
+                })
+        });
+        match plan {
+            Ok(plan) if delivered => Ok(plan.total_time.0),
+            // Left unbounded, the search still ends as no state is expanded
+            // twice
+            _ => Ok(u32::MAX),
+        }
+    }
+
+    // Earliest time every package could be delivered if each had the closest
+    // train all for itself.
+    fn lower_bound(&self, state: &State) -> Option<u32> {
+        let mut bound = state.time;
+        for (package, package_state) in state.packages.iter().enumerate() {
+            let destination = self.destinations[package];
+            let delivery = match package_state {
+                PackageState::Delivered => continue,
+                PackageState::On(train) => match state.trains[*train] {
+                    TrainState::Moving(node, arrival) => {
+                        arrival + self.distances[node][destination]?
+                    }
+                    TrainState::Idle(node) => state.time + self.distances[node][destination]?,
+                },
+                PackageState::At(origin) => {
+                    let pickup = state
+                        .trains
+                        .iter()
+                        .enumerate()
+                        .filter(|(train, _)| self.can_carry(*train, package))
+                        .filter_map(|(_, train_state)| match train_state {
+                            TrainState::Idle(node) => {
+                                Some(state.time + self.distances[*node][*origin]?)
+                            }
+                            TrainState::Moving(node, arrival) => {
+                                Some(arrival + self.distances[*node][*origin]?)
+                            }
+                        })
+                        .min()?;
+                    pickup.max(self.ready_times[package]) + self.distances[*origin][destination]?
+                }
+            };
+            bound = bound.max(delivery);
+        }
+        Some(bound)
+    }
+
+    // Best-first: the partial plan with the lowest lower bound is expanded
+    // first, so the first completed plan taken out of the queue is optimal.
+    fn search(&mut self, state: State) -> Option<OptimalPlan> {
+        let mut plans: Vec<(Option<usize>, Option<PlannedMove>)> = vec![(None, None)];
+        let mut queue = BinaryHeap::new();
+        queue.push(Reverse((
+            self.lower_bound(&state)?,
+            state.time,
+            0,
+            Queued(state),
+        )));
+
+        while let Some(Reverse((_, _, plan, Queued(state)))) = queue.pop() {
+            if state.is_completed() {
+                let mut moves = vec![];
+                let mut plan = Some(plan);
+                while let Some(index) = plan {
+                    let (parent, planned_move) = &plans[index];
+                    moves.extend(planned_move.clone());
+                    plan = *parent;
+                }
+                moves.reverse();
+                return Some(OptimalPlan {
+                    total_time: Minute(state.time),
+                    moves,
+                });
+            }
+
+            let key = state.key(&self.ready_times);
+            if self.seen.get(&key).is_some_and(|time| *time <= state.time) {
+                continue;
+            }
+            self.seen.insert(key, state.time);
+
+            for (child, planned_move) in self.expand(state) {
+                let Some(bound) = self.lower_bound(&child) else {
+                    continue;
+                };
+                if bound > self.bound {
+                    continue;
+                }
+                plans.push((Some(plan), planned_move));
+                queue.push(Reverse((bound, child.time, plans.len() - 1, Queued(child))));
+            }
+        }
+
+        None
+    }
+
+    fn expand(&self, state: State) -> Vec<(State, Option<PlannedMove>)> {
+        let undecided = state
+            .trains
+            .iter()
+            .enumerate()
+            .find_map(|(train, train_state)| match train_state {
+                TrainState::Idle(node) if state.waiting & (1 << train) == 0 => Some((train, *node)),
+                _ => None,
+            });
+        let Some((train, node)) = undecided else {
+            return self
+                .advance(state)
+                .into_iter()
+                .map(|child| (child, None))
+                .collect();
+        };
+
+        let mut children = vec![];
+        let cargo: Vec<usize> = state
+            .packages
+            .iter()
+            .enumerate()
+            .filter(|(package, package_state)| {
+                **package_state == PackageState::At(node)
+                    && self.ready_times[*package] <= state.time
+            })
+            .map(|(package, _)| package)
+            .collect();
+        for (index, &(next, travel_time, track)) in self.edges[node].iter().enumerate() {
+            // A platform is taken from the time a train heads to the station,
+            // so that no train ever queues for one
+            if self.platforms[next].is_some_and(|platforms| {
+                let taken = state
+                    .trains
+                    .iter()
+                    .filter(|train_state| match train_state {
+                        TrainState::Idle(location) | TrainState::Moving(location, _) => {
+                            *location == next
+                        }
+                    })
+                    .count();
+                taken >= platforms as usize
+            }) {
+                continue;
+            }
+
+            for mask in 0..(1u32 << cargo.len()) {
+                let loaded: Vec<usize> = cargo
+                    .iter()
+                    .enumerate()
+                    .filter(|(bit, _)| mask & (1 << bit) != 0)
+                    .map(|(_, package)| *package)
+                    .collect();
+                let weight: u32 = loaded.iter().map(|package| self.weights[*package]).sum();
+                let volume: u64 = loaded
+                    .iter()
+                    .map(|package| u64::from(self.volumes[*package]))
+                    .sum();
+                if weight > self.capacities[train]
+                    || volume > u64::from(self.volume_capacities[train])
+                {
+                    continue;
+                }
+
+                // Loaded here and unloaded at the next station, as in
+                // `deliver_packages`
+                let weight = Kilogram(weight);
+                let loading = self.handling_times[node].get_dwell_time(loaded.len(), &weight);
+                let loaded_at = state.time.saturating_add(loading.0);
+                // Held at the station until its departure time and a track is
+                // free
+                let loaded_at = loaded_at.max(self.departure_times[train]);
+                let departure = match self.tracks[track] {
+                    None => loaded_at,
+                    Some(tracks) => {
+                        let reserved: Vec<(Minute, Minute)> = state
+                            .on_track
+                            .iter()
+                            .flatten()
+                            .filter(|(taken, _, _)| *taken == track)
+                            .map(|(_, from, until)| (Minute(*from), Minute(*until)))
+                            .collect();
+                        find_free_track(&reserved, tracks, Minute(loaded_at), Minute(travel_time))
+                            .0
+                    }
+                };
+                let unloading = self.handling_times[next].get_dwell_time(loaded.len(), &weight);
+                let arrival = departure
+                    .saturating_add(travel_time)
+                    .saturating_add(unloading.0);
+
+                let mut child = state.clone();
+                child.trains[train] = TrainState::Moving(next, arrival);
+                // The platform it leaves is free again, the trains that waited
+                // for it decide anew
+                if self.platforms[node].is_some() {
+                    child.waiting = 0;
+                }
+                child.on_track[train] = self.tracks[track]
+                    .map(|_| (track, departure, departure.saturating_add(travel_time)));
+                for package in &loaded {
+                    child.packages[*package] = PackageState::On(train);
+                }
+                let planned_move = PlannedMove {
+                    departure: Minute(departure),
+                    train: self.train_ids[train].clone(),
+                    origin: self.node_ids[node].clone(),
+                    destination: self.node_ids[next].clone(),
+                    edge: self.edge_ids[node][index].clone(),
+                    packages: loaded
+                        .iter()
+                        .map(|package| self.package_ids[*package].clone())
+                        .collect(),
+                };
+                children.push((child, Some(planned_move)));
+            }
+        }
+
+        let mut child = state;
+        child.waiting |= 1 << train;
+        children.push((child, None));
+        children
+    }
+
+    // Every idle train has decided, jump to the next arrival or package ready
+    // to be picked up, and drop the packages of the trains that arrived.
+    fn advance(&self, mut state: State) -> Option<State> {
+        let arrivals = state
+            .trains
+            .iter()
+            .filter_map(|train_state| match train_state {
+                TrainState::Moving(_, arrival) => Some(*arrival),
+                TrainState::Idle(_) => None,
+            });
+        let ready_times = state
+            .packages
+            .iter()
+            .enumerate()
+            .filter(|(_, package_state)| matches!(package_state, PackageState::At(_)))
+            .map(|(package, _)| self.ready_times[package])
+            .filter(|ready_time| *ready_time > state.time);
+        let time = arrivals.chain(ready_times).min()?;
+
+        state.time = time;
+        state.waiting = 0;
+        for taken in &mut state.on_track {
+            if taken.is_some_and(|(_, _, until)| until <= time) {
+                *taken = None;
+            }
+        }
+        for train in 0..state.trains.len() {
+            let TrainState::Moving(node, arrival) = state.trains[train] else {
+                continue;
+            };
+            if arrival != time {
+                continue;
+            }
+            state.trains[train] = TrainState::Idle(node);
+            for package in 0..state.packages.len() {
+                if state.packages[package] == PackageState::On(train) {
+                    state.packages[package] = if self.destinations[package] == node {
+                        PackageState::Delivered
+                    } else {
+                        PackageState::At(node)
+                    };
+                }
+            }
+        }
+        Some(state)
+    }
+}
+
+// Stops dispatching once the heuristic is back to trains and packages it has
+// already been in, it would go round in circles forever otherwise.
+#[derive(Default)]
+struct LoopGuard {
+    seen: Vec<(Vec<train::Status>, Vec<package::Status>)>,
+    looping: bool,
+}
+
+impl DispatchStrategy for LoopGuard {
+    fn dispatch(&mut self, system: &TrainFreightSystem) -> Vec<TrainAction> {
+        let snapshot = (
+            system
+                .train_handler
+                .trains
+                .iter()
+                .map(|train| train.status.clone())
+                .collect(),
+            system
+                .package_handler
+                .packages
+                .iter()
+                .map(|package| package.status.clone())
+                .collect(),
+        );
+        if self.looping || self.seen.contains(&snapshot) {
+            self.looping = true;
+            return vec![];
+        }
+        self.seen.push(snapshot);
+        DefaultDispatch.dispatch(system)
+    }
+}
+
+// Only the bounds, time and insertion order decide the queue order
+struct Queued(State);
+
+impl PartialEq for Queued {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    fn cmp(&self, _other: &Self) -> std::cmp::Ordering {
+        std::cmp::Ordering::Equal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{train_freight_system::dispatch::DefaultDispatch, util::volume::Liter};
+
+    #[test]
+    fn test_solve() {
+        let mut system = TrainFreightSystem::default();
+        system.add_node("A").expect("can't add node");
+        system.add_node("B").expect("can't add node");
+        system.add_node("C").expect("can't add node");
+        system
+            .add_edge("E1", "A", "B", Minute(30))
+            .expect("can't add edge");
+        system
+            .add_edge("E2", "B", "C", Minute(10))
+            .expect("can't add edge");
+        system
+            .add_package("K1", Kilogram(5), "A", "C")
+            .expect("Can't add package");
+        system
+            .add_train("Q1", Kilogram(5), "B")
+            .expect("Can't add train");
+
+        let plan = solve(&system).expect("can't solve");
+        assert_eq!(plan.total_time, Minute(70));
+        let destinations: Vec<&str> = plan
+            .moves
+            .iter()
+            .map(|planned_move| planned_move.destination.0.as_str())
+            .collect();
+        assert_eq!(destinations, vec!["A", "B", "C"]);
+        assert_eq!(plan.moves[1].packages, vec![PackageId("K1".into())]);
+    }
+
+    #[test]
+    fn test_solve_with_ready_time() {
+        let mut system = TrainFreightSystem::default();
+        system.add_node("A").expect("can't add node");
+        system.add_node("B").expect("can't add node");
+        system
+            .add_edge("E1", "A", "B", Minute(30))
+            .expect("can't add edge");
+        system
+            .add_package("K1", Kilogram(5), "A", "B")
+            .expect("Can't add package");
+        system
+            .set_package_time_window("K1", Some(Minute(20)), None)
+            .expect("Can't set time window");
+        system
+            .add_train("Q1", Kilogram(5), "A")
+            .expect("Can't add train");
+
+        let plan = solve(&system).expect("can't solve");
+        assert_eq!(plan.total_time, Minute(50));
+    }
+
+    #[test]
+    fn test_solve_with_departure_time() {
+        let mut system = TrainFreightSystem::default();
+        system.add_node("A").expect("can't add node");
+        system.add_node("B").expect("can't add node");
+        system
+            .add_edge("E1", "A", "B", Minute(30))
+            .expect("can't add edge");
+        system
+            .add_package("K1", Kilogram(5), "A", "B")
+            .expect("Can't add package");
+        system
+            .add_train("Q1", Kilogram(5), "A")
+            .expect("Can't add train");
+        system
+            .set_train_departure("Q1", Some(Minute(40)))
+            .expect("can't set departure");
+
+        let plan = solve(&system).expect("can't solve");
+        assert_eq!(plan.total_time, Minute(70));
+        assert_eq!(plan.moves[0].departure, Minute(40));
+    }
+
+    #[test]
+    fn test_solve_with_timetable() {
+        let mut system = TrainFreightSystem::default();
+        system.add_node("A").expect("can't add node");
+        system.add_node("B").expect("can't add node");
+        system
+            .add_edge("E1", "A", "B", Minute(30))
+            .expect("can't add edge");
+        system
+            .add_package("K1", Kilogram(5), "A", "B")
+            .expect("Can't add package");
+        system
+            .add_train("Q1", Kilogram(5), "A")
+            .expect("Can't add train");
+        system
+            .add_train("Q2", Kilogram(5), "B")
+            .expect("Can't add train");
+        let stops = vec![
+            (NodeId("A".into()), Minute(100)),
+            (NodeId("B".into()), Minute(130)),
+        ];
+        system
+            .set_train_timetable("Q1", Some(train::Timetable::new(stops, None)))
+            .expect("can't set timetable");
+
+        // The scheduled train is left out, the on-demand one goes for K1
+        let plan = solve(&system).expect("can't solve");
+        assert_eq!(plan.total_time, Minute(60));
+        assert!(plan
+            .moves
+            .iter()
+            .all(|planned_move| planned_move.train == TrainId("Q2".into())));
+    }
+
+    #[test]
+    fn test_solve_with_handling_time() {
+        let mut system = TrainFreightSystem::default();
+        system.add_node("A").expect("can't add node");
+        system.add_node("B").expect("can't add node");
+        system
+            .add_edge("E1", "A", "B", Minute(30))
+            .expect("can't add edge");
+        system
+            .add_package("K1", Kilogram::from_tonnes(5), "A", "B")
+            .expect("Can't add package");
+        system
+            .add_train("Q1", Kilogram::from_tonnes(6), "A")
+            .expect("Can't add train");
+        let handling_time = HandlingTime {
+            per_stop: Minute(5),
+            per_tonne: Minute(1),
+            ..Default::default()
+        };
+        system
+            .set_node_handling_time("A", handling_time.clone())
+            .expect("can't set handling time");
+        system
+            .set_node_handling_time("B", handling_time)
+            .expect("can't set handling time");
+
+        let plan = solve(&system).expect("can't solve");
+        assert_eq!(plan.moves[0].departure, Minute(10));
+        assert_eq!(plan.total_time, Minute(50));
+        assert_not_worse_than_heuristic(&system);
+    }
+
+    #[test]
+    fn test_solve_with_single_track() {
+        let mut system = TrainFreightSystem::default();
+        for node in ["A", "B", "C"] {
+            system.add_node(node).expect("can't add node");
+        }
+        system
+            .add_edge("E1", "A", "B", Minute(30))
+            .expect("can't add edge");
+        system
+            .add_edge("E2", "B", "C", Minute(10))
+            .expect("can't add edge");
+        system.set_edge_tracks("E1", 1).expect("can't set tracks");
+        system
+            .add_package("K1", Kilogram(5), "A", "C")
+            .expect("Can't add package");
+        system
+            .add_package("K2", Kilogram(5), "B", "A")
+            .expect("Can't add package");
+        system
+            .add_train("Q1", Kilogram(5), "A")
+            .expect("Can't add train");
+        system
+            .add_train("Q2", Kilogram(5), "B")
+            .expect("Can't add train");
+
+        // Q2 would take E1 right away without the single track
+        let plan = solve(&system).expect("can't solve");
+        assert_eq!(plan.total_time, Minute(60));
+        let q2_move = plan
+            .moves
+            .iter()
+            .find(|planned_move| planned_move.train.0 == "Q2")
+            .expect("Q2 doesn't move");
+        assert_eq!(q2_move.departure, Minute(30));
+        assert_eq!(q2_move.packages, vec![PackageId("K2".into())]);
+        assert_not_worse_than_heuristic(&system);
+    }
+
+    #[test]
+    fn test_solve_with_platforms() {
+        let mut system = TrainFreightSystem::default();
+        for node in ["A", "B", "C"] {
+            system.add_node(node).expect("can't add node");
+        }
+        system
+            .add_edge("E1", "A", "B", Minute(30))
+            .expect("can't add edge");
+        system
+            .add_edge("E2", "B", "C", Minute(10))
+            .expect("can't add edge");
+        system.set_node_platforms("B", 1).expect("can't set platforms");
+        system
+            .add_package("K1", Kilogram(5), "A", "B")
+            .expect("Can't add package");
+        system
+            .add_train("Q1", Kilogram(5), "A")
+            .expect("Can't add train");
+        system
+            .add_train("Q2", Kilogram(5), "B")
+            .expect("Can't add train");
+
+        // Q1 waits for Q2 to leave B and still heads there at once
+        let plan = assert_not_worse_than_heuristic(&system);
+        assert_eq!(plan.total_time, Minute(30));
+        let trains: Vec<&str> = plan
+            .moves
+            .iter()
+            .map(|planned_move| planned_move.train.0.as_str())
+            .collect();
+        assert_eq!(trains, vec!["Q2", "Q1"]);
+        assert!(plan
+            .moves
+            .iter()
+            .all(|planned_move| planned_move.departure == Minute(0)));
+    }
+
+    fn four_stations() -> TrainFreightSystem {
+        let mut system = TrainFreightSystem::default();
+        for node in ["A", "B", "C", "D"] {
+            system.add_node(node).expect("can't add node");
+        }
+        system
+            .add_edge("E1", "A", "B", Minute(30))
+            .expect("can't add edge");
+        system
+            .add_edge("E2", "B", "C", Minute(10))
+            .expect("can't add edge");
+        system
+            .add_edge("E3", "C", "D", Minute(20))
+            .expect("can't add edge");
+        system
+            .add_package("K1", Kilogram(5), "C", "A")
+            .expect("Can't add package");
+        system
+            .add_package("K2", Kilogram(4), "D", "A")
+            .expect("Can't add package");
+        system
+            .add_train("Q1", Kilogram(10), "B")
+            .expect("Can't add train");
+        system
+    }
+
+    // Replays the plan: each train leaves from where it got to once it is
+    // there, along an edge, with packages waiting at that station that fit,
+    // and every package is at its destination by the end.
+    fn assert_feasible(system: &TrainFreightSystem, plan: &OptimalPlan) {
+        let mut trains: Vec<(TrainId, NodeId, u32)> = system
+            .train_handler
+            .trains
+            .iter()
+            .filter_map(|train| match &train.status {
+                train::Status::StoppedAt(node) | train::Status::QueuedFor(_, node, _) => {
+                    Some((train.id.clone(), node.clone(), 0))
+                }
+                train::Status::DeliveringTo(_, node, _, arrival) => {
+                    Some((train.id.clone(), node.clone(), arrival.0))
+                }
+                train::Status::NotAvailable => None,
+            })
+            .collect();
+        let mut packages: Vec<(PackageId, NodeId, u32)> = system
+            .package_handler
+            .packages
+            .iter()
+            .filter_map(|package| Some((package.id.clone(), package.get_location()?.clone(), 0)))
+            .collect();
+
+        for planned_move in &plan.moves {
+            let (_, location, free) = trains
+                .iter_mut()
+                .find(|(train_id, _, _)| *train_id == planned_move.train)
+                .expect("unknown train");
+            assert_eq!(*location, planned_move.origin);
+            assert!(*free <= planned_move.departure.0);
+            let pos = system.find_node_index_by_id(&planned_move.origin).unwrap();
+            let edge = system.nodes[pos]
+                .find_edge_by_id(&planned_move.edge)
+                .expect("no edge");
+            assert_eq!(edge.node, planned_move.destination);
+            let arrival = planned_move.departure.0 + edge.travel_time.0;
+            assert!(arrival <= plan.total_time.0);
+            *location = planned_move.destination.clone();
+            *free = arrival;
+
+            let mut weight = 0;
+            for package_id in &planned_move.packages {
+                let (_, location, ready) = packages
+                    .iter_mut()
+                    .find(|(id, _, _)| id == package_id)
+                    .expect("unknown package");
+                assert_eq!(*location, planned_move.origin);
+                assert!(*ready <= planned_move.departure.0);
+                *location = planned_move.destination.clone();
+                *ready = arrival;
+                weight += system
+                    .package_handler
+                    .get_package(package_id)
+                    .unwrap()
+                    .weight
+                    .0;
+            }
+            let train = system.train_handler.get_train(&planned_move.train).unwrap();
+            assert!(weight <= train.max_capacity.0);
+        }
+        for (package_id, location, _) in packages {
+            let package = system.package_handler.get_package(&package_id).unwrap();
+            assert_eq!(location, package.destination);
+        }
+    }
+
+    // The solver is the reference for the heuristic, its plan can be carried
+    // out and is never worse
+    fn assert_not_worse_than_heuristic(system: &TrainFreightSystem) -> OptimalPlan {
+        let plan = solve(system).expect("can't solve");
+        assert_feasible(system, &plan);
+        let total_time = system
+            .clone()
+            .deliver_packages(&mut DefaultDispatch)
+            .expect("can't deliver packages")
+            .total_time;
+        assert!(plan.total_time <= total_time);
+        plan
+    }
+
+    #[test]
+    fn test_solve_is_not_worse_than_heuristic() {
+        let plan = assert_not_worse_than_heuristic(&four_stations());
+        assert_eq!(plan.total_time, Minute(90));
+
+        // Packages too heavy for the closest train
+        let mut system = four_stations();
+        system
+            .add_package("K3", Kilogram(8), "B", "D")
+            .expect("Can't add package");
+        system
+            .add_train("Q2", Kilogram(5), "C")
+            .expect("Can't add train");
+        assert_not_worse_than_heuristic(&system);
+
+        // A train still on its way, and one that is not available
+        let mut system = four_stations();
+        system.train_handler.trains[0].status = train::Status::DeliveringTo(
+            NodeId("A".into()),
+            NodeId("B".into()),
+            EdgeId("E1".into()),
+            Minute(20),
+        );
+        system
+            .add_train("Q2", Kilogram(10), "D")
+            .expect("Can't add train");
+        system.train_handler.trains[1].status = train::Status::NotAvailable;
+        let plan = assert_not_worse_than_heuristic(&system);
+        assert_eq!(plan.total_time, Minute(110));
+        assert!(plan
+            .moves
+            .iter()
+            .all(|planned_move| planned_move.train == TrainId("Q1".into())));
+    }
+
+    #[test]
+    fn test_solve_with_multiple_trains() {
+        let mut system = four_stations();
+        system
+            .add_package("K3", Kilogram(6), "A", "D")
+            .expect("Can't add package");
+        system
+            .add_train("Q2", Kilogram(6), "A")
+            .expect("Can't add train");
+
+        let plan = solve(&system).expect("can't solve");
+        assert_eq!(plan.total_time, Minute(90));
+        assert!(plan
+            .moves
+            .iter()
+            .any(|planned_move| planned_move.train == TrainId("Q2".into())
+                && planned_move.packages == vec![PackageId("K3".into())]));
+    }
+
+    #[test]
+    fn test_solve_with_volume() {
+        let mut system = TrainFreightSystem::default();
+        system.add_node("A").expect("can't add node");
+        system.add_node("B").expect("can't add node");
+        system
+            .add_edge("E1", "A", "B", Minute(30))
+            .expect("can't add edge");
+        for package in ["K1", "K2"] {
+            system
+                .add_package(package, Kilogram(1), "A", "B")
+                .expect("Can't add package");
+            system
+                .set_package_volume(package, Some(Liter(300)))
+                .expect("can't update package");
+        }
+        system
+            .add_train("Q1", Kilogram(5), "A")
+            .expect("Can't add train");
+        system
+            .set_train_volume("Q1", Some(Liter(500)))
+            .expect("can't update train");
+
+        // Light enough to go together but only one fits at a time
+        let plan = solve(&system).expect("can't solve");
+        assert_eq!(plan.total_time, Minute(90));
+        assert!(plan
+            .moves
+            .iter()
+            .all(|planned_move| planned_move.packages.len() <= 1));
+
+        system
+            .set_package_volume("K2", Some(Liter(600)))
+            .expect("can't update package");
+        assert!(matches!(
+            solve(&system),
+            Err(Error::NoTrainFits(package)) if package.0 == "K2"
+        ));
+    }
+
+    #[test]
+    fn test_solve_infeasible() {
+        let mut system = TrainFreightSystem::default();
+        system.add_node("A").expect("can't add node");
+        system.add_node("B").expect("can't add node");
+        system.add_node("C").expect("can't add node");
+        system
+            .add_edge("E1", "A", "B", Minute(30))
+            .expect("can't add edge");
+        system
+            .add_package("K1", Kilogram(5), "A", "C")
+            .expect("Can't add package");
+        system
+            .add_train("Q1", Kilogram(5), "B")
+            .expect("Can't add train");
+        assert!(matches!(
+            solve(&system),
+            Err(Error::Unreachable { package, from, to })
+                if package.0 == "K1" && from.0 == "A" && to.0 == "C"
+        ));
+
+        system
+            .add_edge("E2", "B", "C", Minute(10))
+            .expect("can't add edge");
+        system
+            .add_package("K2", Kilogram(50), "B", "A")
+            .expect("Can't add package");
+        assert!(matches!(
+            solve(&system),
+            Err(Error::NoTrainFits(package)) if package.0 == "K2"
+        ));
+    }
+}
@@ -0,0 +1,224 @@
+use std::{fs, path::Path};
+
+use super::{
+    error::Result,
+    package::PackageId,
+    plan::{format_time, DeliveryPlan},
+    train::TrainId,
+};
+
+const LABEL_WIDTH: u32 = 80;
+const CHART_WIDTH: u32 = 800;
+const AXIS_HEIGHT: u32 = 30;
+const LANE_HEIGHT: u32 = 40;
+const BAR_HEIGHT: u32 = 24;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Minutes between two ticks of the time axis, ten of them at the most
+fn get_tick_step(total_time: u32) -> u32 {
+    let mut magnitude = 1;
+    loop {
+        for factor in [1, 2, 5] {
+            let step = factor * magnitude;
+            if total_time / step <= 10 {
+                return step;
+            }
+        }
+        magnitude *= 10;
+    }
+}
+
+// Trains in the order they first show up in the plan
+fn list_lanes(plan: &DeliveryPlan) -> Vec<&TrainId> {
+    let mut lanes: Vec<&TrainId> = vec![];
+    let trains = plan
+        .moves
+        .iter()
+        .map(|train_move| &train_move.train)
+        .chain(plan.waits.iter().map(|wait| &wait.train));
+    for train_id in trains {
+        if !lanes.contains(&train_id) {
+            lanes.push(train_id);
+        }
+    }
+    lanes
+}
+
+/// SVG timeline of the plan with one lane per train. Moves are bars labelled
+/// with their stations and the packages on board, waits are grey bars, and
+/// the circles mark where packages are first picked up (green) and delivered
+/// (red). Every shape has a tooltip with the details.
+pub fn render(plan: &DeliveryPlan) -> String {
+    let lanes = list_lanes(plan);
+    let start = plan.start.as_ref();
+    let end = plan
+        .moves
+        .iter()
+        .map(|train_move| train_move.arrival.0)
+        .chain(plan.waits.iter().map(|wait| wait.until.0))
+        .fold(plan.total_time.0, u32::max)
+        .max(1);
+    let scale = f64::from(CHART_WIDTH) / f64::from(end);
+    let x = |time: u32| f64::from(LABEL_WIDTH) + f64::from(time) * scale;
+    let lane_y = |train_id: &TrainId| {
+        let lane = lanes.iter().position(|lane| *lane == train_id).unwrap() as u32;
+        AXIS_HEIGHT + lane * LANE_HEIGHT + (LANE_HEIGHT - BAR_HEIGHT) / 2
+    };
+    let width = LABEL_WIDTH + CHART_WIDTH + 20;
+    let height = AXIS_HEIGHT + LANE_HEIGHT * lanes.len() as u32 + 10;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         font-family=\"sans-serif\" font-size=\"12\">\n"
+    );
+
+    let step = get_tick_step(end);
+    for time in (0..=end).step_by(step as usize) {
+        svg += &format!(
+            "  <line x1=\"{0:.1}\" y1=\"{1}\" x2=\"{0:.1}\" y2=\"{2}\" stroke=\"#ddd\"/>\n\
+             \x20 <text x=\"{0:.1}\" y=\"{3}\" text-anchor=\"middle\">W={4}</text>\n",
+            x(time),
+            AXIS_HEIGHT,
+            height - 10,
+            AXIS_HEIGHT - 10,
+            time
+        );
+    }
+    for train_id in &lanes {
+        svg += &format!(
+            "  <text x=\"4\" y=\"{}\">{}</text>\n",
+            lane_y(train_id) + BAR_HEIGHT / 2 + 4,
+            escape(&train_id.0)
+        );
+    }
+
+    for wait in &plan.waits {
+        svg += &format!(
+            "  <rect x=\"{:.1}\" y=\"{}\" width=\"{:.1}\" height=\"{}\" fill=\"#bbb\">\
+             <title>{} waits at {} for {} from {} until {}</title></rect>\n",
+            x(wait.from.0),
+            lane_y(&wait.train),
+            x(wait.until.0) - x(wait.from.0),
+            BAR_HEIGHT,
+            escape(&wait.train.0),
+            escape(&wait.station.0),
+            escape(&wait.edge.0),
+            format_time(start, &wait.from),
+            format_time(start, &wait.until)
+        );
+    }
+
+    let mut picked_up: Vec<&PackageId> = vec![];
+    for train_move in &plan.moves {
+        let y = lane_y(&train_move.train);
+        let packages: Vec<&str> = train_move
+            .picked_up
+            .iter()
+            .map(|package_id| package_id.0.as_str())
+            .collect();
+        let mut label = format!("{}→{}", train_move.origin.0, train_move.destination.0);
+        if !packages.is_empty() {
+            label += &format!(" {}", packages.join(","));
+        }
+        svg += &format!(
+            "  <g><title>{} from {} at {} to {} at {} along {} with [{}]</title>\
+             <rect x=\"{:.1}\" y=\"{}\" width=\"{:.1}\" height=\"{}\" fill=\"#8ab4e8\" stroke=\"#3a6ea5\"/>\
+             <text x=\"{:.1}\" y=\"{}\">{}</text></g>\n",
+            escape(&train_move.train.0),
+            escape(&train_move.origin.0),
+            format_time(start, &train_move.departure),
+            escape(&train_move.destination.0),
+            format_time(start, &train_move.arrival),
+            escape(&train_move.edge.0),
+            escape(&packages.join(", ")),
+            x(train_move.departure.0),
+            y,
+            x(train_move.arrival.0) - x(train_move.departure.0),
+            BAR_HEIGHT,
+            x(train_move.departure.0) + 3.0,
+            y + BAR_HEIGHT / 2 + 4,
+            escape(&label)
+        );
+
+        for package_id in &train_move.picked_up {
+            if picked_up.contains(&package_id) {
+                continue;
+            }
+            picked_up.push(package_id);
+            svg += &format!(
+                "  <circle cx=\"{:.1}\" cy=\"{}\" r=\"4\" fill=\"#2a2\">\
+                 <title>{} picked up at {} at {}</title></circle>\n",
+                x(train_move.departure.0),
+                y,
+                escape(&package_id.0),
+                escape(&train_move.origin.0),
+                format_time(start, &train_move.departure)
+            );
+        }
+    }
+
+    for delivery in &plan.deliveries {
+        // Packages already at their destination have no train
+        if !lanes.contains(&&delivery.train) {
+            continue;
+        }
+        svg += &format!(
+            "  <circle cx=\"{:.1}\" cy=\"{}\" r=\"4\" fill=\"#d22\">\
+             <title>{} delivered by {} at {}</title></circle>\n",
+            x(delivery.time.0),
+            lane_y(&delivery.train) + BAR_HEIGHT,
+            escape(&delivery.package.0),
+            escape(&delivery.train.0),
+            format_time(start, &delivery.time)
+        );
+    }
+
+    svg += "</svg>\n";
+    svg
+}
+
+pub fn write(plan: &DeliveryPlan, path: impl AsRef<Path>) -> Result<()> {
+    fs::write(path, render(plan))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        train_freight_system::{dispatch::DefaultDispatch, TrainFreightSystem},
+        util::{kilogram::Kilogram, minute::Minute},
+    };
+
+    #[test]
+    fn test_render() {
+        let mut system = TrainFreightSystem::default();
+        for node in ["A", "B", "C"] {
+            system.add_node(node).expect("can't add node");
+        }
+        system.add_edge("E1", "A", "B", Minute(30)).expect("can't add edge");
+        system.add_edge("E2", "B", "C", Minute(10)).expect("can't add edge");
+        system.add_package("K1", Kilogram(5), "A", "C").expect("Can't add package");
+        system.add_package("K<2>", Kilogram(1), "C", "B").expect("Can't add package");
+        system.add_train("Q1", Kilogram(6), "B").expect("Can't add train");
+        system.add_train("Q2", Kilogram(6), "C").expect("Can't add train");
+        let plan = system.deliver_packages(&mut DefaultDispatch).expect("can't deliver packages");
+
+        let svg = render(&plan);
+        assert!(svg.starts_with("<svg ") && svg.ends_with("</svg>\n"));
+        assert_eq!(list_lanes(&plan).len(), 2);
+        assert_eq!(svg.matches("<rect ").count(), plan.moves.len());
+        assert_eq!(svg.matches("fill=\"#2a2\"").count(), 2);
+        assert_eq!(svg.matches("fill=\"#d22\"").count(), plan.deliveries.len());
+        assert!(svg.contains("A→B K1</text>"));
+        assert!(svg.contains("K&lt;2&gt; picked up at C"));
+        assert_eq!(get_tick_step(70), 10);
+        assert_eq!(get_tick_step(7), 1);
+    }
+}
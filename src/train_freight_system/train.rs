@@ -1,28 +1,163 @@
-use crate::util::{kilogram::Kilogram, minute::Minute};
+use serde::{Deserialize, Serialize};
+
+use crate::util::{kilogram::Kilogram, minute::Minute, volume::Liter};
 
 use super::{
-    error::{Error, ErrorKind, Result},
+    edge::EdgeId,
+    error::{Error, Result},
     node::NodeId,
     package::{self, Package},
 };
 
-#[derive(Debug, Default, PartialEq, Clone)]
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Status {
     #[default]
     NotAvailable,
     StoppedAt(NodeId),
-    DeliveringTo(NodeId, NodeId, Minute),
+    // Moving from station(NodeId) to station(NodeId) along edge(EdgeId),
+    // arriving at time(Minute) counted from the start of the delivery run
+    DeliveringTo(NodeId, NodeId, EdgeId, Minute),
+    // Arrived along edge(EdgeId) from station(NodeId) and waiting for a
+    // platform at station(NodeId)
+    QueuedFor(NodeId, NodeId, EdgeId),
 }
 
-#[derive(Debug, Default, PartialEq, Clone)]
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 pub struct TrainId(pub String);
 
-#[derive(Debug, Default, Clone)]
+/// Leg of a scheduled service, with the time the train leaves its origin and
+/// is due at its destination counted from the start of the delivery run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Leg {
+    pub origin: NodeId,
+    pub destination: NodeId,
+    pub departure: Minute,
+    pub arrival: Minute,
+}
+
+/// Stops of a scheduled service, each with the time the train leaves it
+/// counted from the start of the first delivery run, the time of the last
+/// one being when the train is due there. With a `period` the service runs
+/// again that long after each start, its last stop then being its first one.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Timetable {
+    pub stops: Vec<(NodeId, Minute)>,
+    #[serde(default)]
+    pub period: Option<Minute>,
+    // Legs the train left on so far, those of the earlier rounds included
+    #[serde(default)]
+    departed: usize,
+    // Time of the delivery runs since the first one
+    #[serde(default)]
+    elapsed: Minute,
+}
+
+impl Timetable {
+    pub fn new(stops: Vec<(NodeId, Minute)>, period: Option<Minute>) -> Self {
+        Self {
+            stops,
+            period,
+            ..Default::default()
+        }
+    }
+
+    fn count_legs(&self) -> usize {
+        self.stops.len().saturating_sub(1)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.period.is_none() && self.departed >= self.count_legs()
+    }
+
+    pub fn calls_at(&self, node_id: &NodeId) -> bool {
+        self.stops.iter().any(|(stop, _)| stop == node_id)
+    }
+
+    // Leg(leg) counted from the first one of the first round
+    fn get_leg(&self, leg: usize) -> Option<Leg> {
+        let legs = self.count_legs();
+        if legs == 0 || (self.period.is_none() && leg >= legs) {
+            return None;
+        }
+        let round = u32::try_from(leg / legs).unwrap_or(u32::MAX);
+        let offset = Minute(
+            self.period
+                .as_ref()
+                .map_or(0, |period| period.0.saturating_mul(round)),
+        );
+        let stop = leg % legs;
+        let time = |stop: usize| self.stops[stop].1.clone() + offset.clone() - self.elapsed.clone();
+        Some(Leg {
+            origin: self.stops[stop].0.clone(),
+            destination: self.stops[stop + 1].0.clone(),
+            departure: time(stop),
+            arrival: time(stop + 1),
+        })
+    }
+
+    /// Leg the train leaves on next, `None` once the timetable is over.
+    pub fn next_leg(&self) -> Option<Leg> {
+        self.get_leg(self.departed)
+    }
+
+    /// Legs from the next one on, two whole rounds of a repeated service so
+    /// that the stops passed in this round are reached again in the next.
+    pub fn list_upcoming_legs(&self) -> Vec<Leg> {
+        let rounds = if self.period.is_some() { 2 } else { 1 };
+        (self.departed..self.departed + rounds * self.count_legs())
+            .map_while(|leg| self.get_leg(leg))
+            .collect()
+    }
+
+    /// Whether a package at `origin` can ride the service to `destination`
+    /// along the upcoming legs, as booked in `deliver_packages`.
+    pub fn can_carry(&self, origin: &NodeId, destination: &NodeId) -> bool {
+        let legs = self.list_upcoming_legs();
+        let Some(first) = legs.iter().position(|leg| leg.origin == *origin) else {
+            return false;
+        };
+        legs[first..]
+            .iter()
+            .any(|leg| leg.destination == *destination)
+    }
+
+    pub fn depart(&mut self) {
+        self.departed += 1;
+    }
+
+    /// Makes the times count from `time`, see `TrainHandler::shift_arrivals`.
+    pub fn shift(&mut self, time: &Minute) {
+        self.elapsed = self.elapsed.clone() + time.clone();
+        // Rounds left behind are dropped so that the counts stay small
+        let legs = self.count_legs();
+        let Some(period) = self.period.clone().filter(|period| period.0 > 0) else {
+            return;
+        };
+        while legs > 0 && self.departed >= legs && self.elapsed >= period {
+            self.departed -= legs;
+            self.elapsed = self.elapsed.clone() - period.clone();
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Train {
     pub id: TrainId,
     pub max_capacity: Kilogram,
     pub status: Status,
     pub load_size: Kilogram,
+    // Room for packages by volume, any volume fits when None
+    #[serde(default)]
+    pub max_volume: Option<Liter>,
+    #[serde(default)]
+    pub load_volume: Liter,
+    // Counted from the start of the delivery run, leaves right away when None
+    #[serde(default)]
+    pub earliest_departure: Option<Minute>,
+    // Service the train keeps to, it is dispatched on demand when None or
+    // once its timetable is over
+    #[serde(default)]
+    pub timetable: Option<Timetable>,
 }
 
 impl Train {
@@ -38,6 +173,12 @@ impl Train {
         self.status = Status::StoppedAt(location);
     }
 
+    pub fn is_scheduled(&self) -> bool {
+        self.timetable
+            .as_ref()
+            .is_some_and(|timetable| !timetable.is_finished())
+    }
+
     pub fn get_location(&self) -> Option<NodeId> {
         match &self.status {
             Status::StoppedAt(node) => Some(node.clone()),
@@ -45,16 +186,51 @@ impl Train {
         }
     }
 
+    /// Station the train is stopped at, or the ones it is between.
+    pub fn list_stations(&self) -> Vec<&NodeId> {
+        match &self.status {
+            Status::NotAvailable => vec![],
+            Status::StoppedAt(node_id) => vec![node_id],
+            Status::DeliveringTo(origin, destination, _, _)
+            | Status::QueuedFor(origin, destination, _) => vec![origin, destination],
+        }
+    }
+
+    /// Edge the train is moving along or queued at the end of.
+    pub fn get_edge(&self) -> Option<&EdgeId> {
+        match &self.status {
+            Status::DeliveringTo(_, _, edge_id, _) | Status::QueuedFor(_, _, edge_id) => {
+                Some(edge_id)
+            }
+            Status::NotAvailable | Status::StoppedAt(_) => None,
+        }
+    }
+
     pub fn load_package(&mut self, package: &mut Package) {
-        let available_size = self.max_capacity.clone() - self.load_size.clone();
-        if package.weight <= available_size {
+        if self.can_accomodate_package(package) {
             self.load_size = self.load_size.clone() + package.weight.clone();
+            self.load_volume = self.load_volume.clone() + package.get_volume();
             package.status = package::Status::LoadedTo(self.id.clone());
         }
     }
 
-    pub fn move_to(&mut self, origin: &NodeId, destination: &NodeId, travel_time: Minute) {
-        self.status = Status::DeliveringTo(origin.clone(), destination.clone(), travel_time);
+    pub fn move_to(
+        &mut self,
+        origin: &NodeId,
+        destination: &NodeId,
+        edge_id: &EdgeId,
+        arrival: Minute,
+    ) {
+        self.status = Status::DeliveringTo(
+            origin.clone(),
+            destination.clone(),
+            edge_id.clone(),
+            arrival,
+        );
+    }
+
+    pub fn queue_for(&mut self, origin: &NodeId, destination: &NodeId, edge_id: &EdgeId) {
+        self.status = Status::QueuedFor(origin.clone(), destination.clone(), edge_id.clone());
     }
 
     pub fn stopped(&mut self, node: &NodeId) {
@@ -68,16 +244,25 @@ impl Train {
             package.status = package::Status::DroppedAt(node.clone(), self.id.clone());
         }
 
+        self.take_off(package);
+    }
+
+    // Frees the room the package took
+    pub fn take_off(&mut self, package: &Package) {
         self.load_size = self.load_size.clone() - package.weight.clone();
+        self.load_volume = self.load_volume.clone() - package.get_volume();
     }
 
     pub fn can_accomodate_package(&self, package: &Package) -> bool {
         let available_size = self.max_capacity.clone() - self.load_size.clone();
-        available_size >= package.weight
+        let has_room = self.max_volume.as_ref().is_none_or(|max_volume| {
+            max_volume.clone() - self.load_volume.clone() >= package.get_volume()
+        });
+        available_size >= package.weight && has_room
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct TrainHandler {
     pub trains: Vec<Train>,
 }
@@ -93,11 +278,7 @@ impl TrainHandler {
         train.set_location(location.clone());
 
         if self.find_train_index_by_name(name).is_some() {
-            return Err(Error::new(
-                ErrorKind::AddTrainError,
-                format!("Train '{name}' already existing"),
-            )
-            .into());
+            return Err(Error::DuplicateTrain(TrainId(name.into())));
         }
         self.trains.push(train);
         Ok(())
@@ -107,6 +288,17 @@ impl TrainHandler {
         self.trains.iter().find(|train| train.id == *train_id)
     }
 
+    pub fn get_train_mut(&mut self, train_id: &TrainId) -> Option<&mut Train> {
+        self.trains.iter_mut().find(|train| train.id == *train_id)
+    }
+
+    pub fn remove_train(&mut self, train_id: &TrainId) -> Result<Train> {
+        let pos = self
+            .find_train_index_by_id(train_id)
+            .ok_or_else(|| Error::UnknownTrain(train_id.clone()))?;
+        Ok(self.trains.remove(pos))
+    }
+
     fn find_train_index_by_name(&self, train_name: &str) -> Option<usize> {
         let train_id = TrainId(train_name.into());
         self.find_train_index_by_id(&train_id)
@@ -116,10 +308,14 @@ impl TrainHandler {
         self.trains.iter().position(|train| train.id == *train_id)
     }
 
+    // Scheduled trains are left out of the stopped ones, they keep to their
+    // timetable
     fn list_trains_stopped_at_node(&self, node_id: &NodeId) -> Vec<&Train> {
         self.trains
             .iter()
-            .filter(|train| train.status == Status::StoppedAt(node_id.clone()))
+            .filter(|train| {
+                train.status == Status::StoppedAt(node_id.clone()) && !train.is_scheduled()
+            })
             .collect()
     }
 
@@ -139,49 +335,106 @@ impl TrainHandler {
         biggest_train_index.map(|index| trains[index].id.clone())
     }
 
-    pub fn get_moving_train_lowest_travel_time(&self) -> Option<Minute> {
+    pub fn list_moving_trains(&self) -> Vec<(TrainId, NodeId, NodeId, EdgeId, Minute)> {
         self.trains
             .iter()
             .filter_map(|train| match &train.status {
-                Status::DeliveringTo(_, _, travel_time) => Some(travel_time.clone()),
+                Status::DeliveringTo(origin, destination, edge_id, arrival) => Some((
+                    train.id.clone(),
+                    origin.clone(),
+                    destination.clone(),
+                    edge_id.clone(),
+                    arrival.clone(),
+                )),
                 _ => None,
             })
-            .min()
+            .collect()
     }
 
-    pub fn time_elapsed(&mut self, duration: &Minute) {
+    /// Makes the arrival and departure times of the trains count from `time`,
+    /// so that the next delivery run carries on from where this one stopped.
+    pub fn shift_arrivals(&mut self, time: &Minute) {
         for train in &mut self.trains {
-            if let Status::DeliveringTo(origin, destination, travel_time) = &train.status.clone() {
-                let remaining_time = travel_time.clone() - duration.clone();
-                if remaining_time == Minute(0) {
-                    train.stopped(destination);
-                } else {
-                    train.move_to(origin, destination, remaining_time);
-                }
+            if let Status::DeliveringTo(_, _, _, arrival) = &mut train.status {
+                *arrival = arrival.clone() - time.clone();
+            }
+            train.earliest_departure = train
+                .earliest_departure
+                .take()
+                .filter(|departure| departure > time)
+                .map(|departure| departure - time.clone());
+            if let Some(timetable) = &mut train.timetable {
+                timetable.shift(time);
             }
         }
     }
 
+    pub fn list_scheduled_trains(&self) -> Vec<TrainId> {
+        self.trains
+            .iter()
+            .filter(|train| train.is_scheduled())
+            .map(|train| train.id.clone())
+            .collect()
+    }
+
+    /// Stopped trains that can be dispatched, the scheduled ones left out.
     pub fn list_stopped_trains(&self) -> Vec<TrainId> {
         self.trains
             .iter()
-            .filter(|train| matches!(&train.status, Status::StoppedAt(_)))
+            .filter(|train| matches!(&train.status, Status::StoppedAt(_)) && !train.is_scheduled())
             .map(|train| train.id.clone())
             .collect()
     }
 
-    pub fn unload_packages_in_trains_that_stopped(&mut self, packages: &mut [Package]) {
-        for train_id in &self.list_stopped_trains() {
-            for package in packages.iter_mut() {
-                if package.is_package_loaded_in_train(train_id) {
-                    let pos = self.find_train_index_by_id(train_id).unwrap();
-                    let node_id = &self.trains[pos].get_location().unwrap();
-                    self.trains[pos].unload_package(package, node_id);
-                }
-            }
+    pub fn unload_package(&mut self, train_id: &TrainId, package: &mut Package) {
+        let pos = self.find_train_index_by_id(train_id).unwrap();
+        if let Some(node_id) = self.trains[pos].get_location() {
+            self.trains[pos].unload_package(package, &node_id);
         }
     }
 
+    pub fn stopped(&mut self, train_id: &TrainId, node_id: &NodeId) {
+        let pos = self.find_train_index_by_id(train_id).unwrap();
+        self.trains[pos].stopped(node_id);
+    }
+
+    pub fn queue_for(
+        &mut self,
+        train_id: &TrainId,
+        origin: &NodeId,
+        destination: &NodeId,
+        edge_id: &EdgeId,
+    ) {
+        let pos = self.find_train_index_by_id(train_id).unwrap();
+        self.trains[pos].queue_for(origin, destination, edge_id);
+    }
+
+    pub fn list_queued_trains(&self) -> Vec<(TrainId, NodeId, NodeId)> {
+        self.trains
+            .iter()
+            .filter_map(|train| match &train.status {
+                Status::QueuedFor(origin, destination, _) => {
+                    Some((train.id.clone(), origin.clone(), destination.clone()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Trains stopped at the station and, with `incoming`, the ones on their
+    /// way to it or queued for it.
+    pub fn count_trains_at_node(&self, node_id: &NodeId, incoming: bool) -> usize {
+        self.trains
+            .iter()
+            .filter(|train| match &train.status {
+                Status::StoppedAt(location) => location == node_id,
+                Status::DeliveringTo(_, destination, _, _)
+                | Status::QueuedFor(_, destination, _) => incoming && destination == node_id,
+                Status::NotAvailable => false,
+            })
+            .count()
+    }
+
     pub fn load_package(&mut self, train_id: &TrainId, package: &mut Package) {
         let pos = self.find_train_index_by_id(train_id).unwrap();
         self.trains[pos].load_package(package);
@@ -192,17 +445,18 @@ impl TrainHandler {
         train_id: &TrainId,
         origin: &NodeId,
         destination: &NodeId,
-        travel_time: Minute,
+        edge_id: &EdgeId,
+        arrival: Minute,
     ) {
         let pos = self.find_train_index_by_id(train_id).unwrap();
-        self.trains[pos].move_to(origin, destination, travel_time);
+        self.trains[pos].move_to(origin, destination, edge_id, arrival);
     }
 
     pub fn list_stopped_trains_at_node(&mut self, node_id: &NodeId) -> Vec<TrainId> {
         self.trains
             .iter_mut()
             .filter(|train| match &train.status {
-                Status::StoppedAt(id) => id == node_id,
+                Status::StoppedAt(id) => id == node_id && !train.is_scheduled(),
                 _ => false,
             })
             .map(|train| train.id.clone())
@@ -1,2 +1,56 @@
+use std::fmt;
+
+pub mod datetime;
 pub mod kilogram;
 pub mod minute;
+pub mod volume;
+
+/// Quantity that can't be read as a number of the unit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseQuantityError(pub String);
+
+impl std::error::Error for ParseQuantityError {}
+
+impl fmt::Display for ParseQuantityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid quantity '{}'", self.0)
+    }
+}
+
+// Reads a plain number of base units, or numbers each followed by a unit
+// like `5kg` or `1h30m`. Units come with how many base units they are worth
+// and are matched ignoring case.
+fn parse_quantity(text: &str, units: &[(&str, u32)]) -> Result<u32, ParseQuantityError> {
+    let invalid = || ParseQuantityError(text.into());
+    let mut rest = text.trim();
+    if let Ok(value) = rest.parse::<u32>() {
+        return Ok(value);
+    }
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut total: u32 = 0;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let value: u32 = rest[..digits].parse().map_err(|_| invalid())?;
+        rest = &rest[digits..];
+        // Longest match first, so that `min` isn't read as `m`
+        let (unit, factor) = units
+            .iter()
+            .filter(|(unit, _)| {
+                rest.get(..unit.len())
+                    .is_some_and(|prefix| prefix.eq_ignore_ascii_case(unit))
+            })
+            .max_by_key(|(unit, _)| unit.len())
+            .ok_or_else(invalid)?;
+        rest = rest[unit.len()..].trim_start();
+        total = value
+            .checked_mul(*factor)
+            .and_then(|value| total.checked_add(value))
+            .ok_or_else(invalid)?;
+    }
+    Ok(total)
+}
@@ -0,0 +1,203 @@
+use std::{fmt, ops::Add, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use super::minute::{Minute, MINUTES_PER_DAY, MINUTES_PER_HOUR};
+
+/// Date and time that can't be read as `YYYY-MM-DD HH:MM`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseDateTimeError(pub String);
+
+impl std::error::Error for ParseDateTimeError {}
+
+impl fmt::Display for ParseDateTimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid date and time '{}'", self.0)
+    }
+}
+
+// Days since 1970-01-01 of a day of the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    // Counted from March so that the leap day is the last of the year
+    let month = i64::from((month + 9) % 12);
+    let day_of_year = (153 * month + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month + 2) / 5 + 1) as u32;
+    let month = ((month + 2) % 12 + 1) as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Calendar date and time to the minute, kept as minutes since 1970-01-01
+/// 00:00. Time zones are left out, every time is taken in the same one.
+#[derive(Debug, Default, Clone, PartialEq, PartialOrd, Ord, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct DateTime(pub i64);
+
+impl DateTime {
+    pub fn new(year: i64, month: u32, day: u32, hour: u32, minute: u32) -> Option<Self> {
+        if !(1..=12).contains(&month)
+            || !(1..=days_in_month(year, month)).contains(&day)
+            || hour >= 24
+            || minute >= 60
+        {
+            return None;
+        }
+        let days = days_from_civil(year, month, day);
+        Some(Self(
+            days * i64::from(MINUTES_PER_DAY)
+                + i64::from(hour * MINUTES_PER_HOUR)
+                + i64::from(minute),
+        ))
+    }
+
+    /// `HH:MM` clock time on the day of this time.
+    pub fn at_clock_time(&self, text: &str) -> Result<Self, ParseDateTimeError> {
+        let invalid = || ParseDateTimeError(text.into());
+        let (hour, minute) = text.trim().split_once(':').ok_or_else(invalid)?;
+        let number = |field: &str| field.parse::<u32>().map_err(|_| invalid());
+        let (hour, minute) = (number(hour)?, number(minute)?);
+        if hour >= 24 || minute >= 60 {
+            return Err(invalid());
+        }
+        let midnight = self.0 - self.0.rem_euclid(i64::from(MINUTES_PER_DAY));
+        Ok(Self(
+            midnight + i64::from(hour * MINUTES_PER_HOUR) + i64::from(minute),
+        ))
+    }
+
+    /// Minutes from `earlier` until this time, `None` when it is earlier.
+    pub fn minutes_since(&self, earlier: &DateTime) -> Option<Minute> {
+        u32::try_from(self.0 - earlier.0).ok().map(Minute)
+    }
+}
+
+impl Add<Minute> for DateTime {
+    type Output = Self;
+
+    fn add(self, rhs: Minute) -> Self::Output {
+        Self(self.0 + i64::from(rhs.0))
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let minutes_per_day = i64::from(MINUTES_PER_DAY);
+        let (year, month, day) = civil_from_days(self.0.div_euclid(minutes_per_day));
+        let minutes = self.0.rem_euclid(minutes_per_day) as u32;
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02} {:02}:{:02}",
+            minutes / MINUTES_PER_HOUR,
+            minutes % MINUTES_PER_HOUR
+        )
+    }
+}
+
+/// Date and time from `2024-05-01 08:30`, `2024-05-01T08:30` or `2024-05-01`
+/// for midnight.
+impl FromStr for DateTime {
+    type Err = ParseDateTimeError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseDateTimeError(text.into());
+        let (date, time) = match text.trim().split_once([' ', 'T', 't']) {
+            Some((date, time)) => (date, time.trim()),
+            None => (text.trim(), "00:00"),
+        };
+        let date: Vec<&str> = date.split('-').collect();
+        let [year, month, day] = date[..] else {
+            return Err(invalid());
+        };
+        let (hour, minute) = time.split_once(':').ok_or_else(invalid)?;
+        if year.len() != 4 {
+            return Err(invalid());
+        }
+        let number = |field: &str| field.parse::<u32>().map_err(|_| invalid());
+        DateTime::new(
+            i64::from(number(year)?),
+            number(month)?,
+            number(day)?,
+            number(hour)?,
+            number(minute)?,
+        )
+        .ok_or_else(invalid)
+    }
+}
+
+impl From<DateTime> for String {
+    fn from(datetime: DateTime) -> Self {
+        datetime.to_string()
+    }
+}
+
+impl TryFrom<String> for DateTime {
+    type Error = ParseDateTimeError;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_datetime() {
+        assert_eq!(DateTime::new(1970, 1, 1, 0, 0), Some(DateTime(0)));
+        assert_eq!(DateTime::new(1970, 1, 2, 1, 30), Some(DateTime(1530)));
+        assert_eq!(DateTime::new(2023, 2, 29, 0, 0), None);
+        assert_eq!(DateTime::new(2024, 4, 31, 0, 0), None);
+        assert_eq!(DateTime::new(2024, 1, 1, 24, 0), None);
+
+        let start: DateTime = "2024-02-28 23:30".parse().unwrap();
+        assert_eq!((start.clone() + Minute(60)).to_string(), "2024-02-29 00:30");
+        assert_eq!(
+            (start.clone() + Minute(1500)).to_string(),
+            "2024-03-01 00:30"
+        );
+        assert_eq!("1969-12-31".parse::<DateTime>().unwrap().0, -1440);
+        assert_eq!(DateTime(-1).to_string(), "1969-12-31 23:59");
+        let later: DateTime = "2024-02-29T01:00".parse().unwrap();
+        assert_eq!(later.minutes_since(&start), Some(Minute(90)));
+        assert_eq!(start.minutes_since(&later), None);
+        for text in ["", "2024-02-30", "24-02-01", "2024-02-01 8", "24:00"] {
+            assert!(text.parse::<DateTime>().is_err(), "{text}");
+        }
+        assert_eq!(
+            start.at_clock_time("06:00").unwrap().to_string(),
+            "2024-02-28 06:00"
+        );
+        for text in ["6", "24:00", "06:60", "2024-02-28 06:00"] {
+            assert!(start.at_clock_time(text).is_err(), "{text}");
+        }
+
+        let json = serde_json::to_string(&start).unwrap();
+        assert_eq!(json, "\"2024-02-28 23:30\"");
+        assert_eq!(serde_json::from_str::<DateTime>(&json).unwrap(), start);
+    }
+}
@@ -1,13 +1,51 @@
-use std::ops::{Add, Sub};
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{Add, Sub},
+    str::FromStr,
+};
 
-#[derive(Debug, Default, Clone, PartialOrd, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+use super::{parse_quantity, ParseQuantityError};
+
+pub const KILOGRAMS_PER_TONNE: u32 = 1000;
+
+#[derive(Debug, Default, Clone, PartialOrd, PartialEq, Serialize, Deserialize)]
 pub struct Kilogram(pub u32);
 
+impl Kilogram {
+    pub fn from_tonnes(tonnes: u32) -> Self {
+        Self(tonnes.saturating_mul(KILOGRAMS_PER_TONNE))
+    }
+
+    pub fn as_tonnes(&self) -> f64 {
+        f64::from(self.0) / f64::from(KILOGRAMS_PER_TONNE)
+    }
+
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Self)
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Self)
+    }
+
+    pub fn saturating_add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+}
+
+// Saturating, an inconsistent load stays at zero instead of wrapping around
 impl Sub for Kilogram {
     type Output = Self;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        Self(self.0 - rhs.0)
+        self.saturating_sub(rhs)
     }
 }
 
@@ -15,6 +53,61 @@ impl Add for Kilogram {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        Self(self.0 + rhs.0)
+        self.saturating_add(rhs)
+    }
+}
+
+impl Sum for Kilogram {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Kilogram> for Kilogram {
+    fn sum<I: Iterator<Item = &'a Kilogram>>(iter: I) -> Self {
+        iter.cloned().sum()
+    }
+}
+
+impl fmt::Display for Kilogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}kg", self.0)
+    }
+}
+
+/// Kilograms from `5`, `5kg`, `2t` or `2t500kg`.
+impl FromStr for Kilogram {
+    type Err = ParseQuantityError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        parse_quantity(text, &[("kg", 1), ("t", KILOGRAMS_PER_TONNE)]).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kilogram() {
+        assert_eq!(Kilogram(2) - Kilogram(5), Kilogram(0));
+        assert_eq!(Kilogram(u32::MAX) + Kilogram(1), Kilogram(u32::MAX));
+        assert_eq!(Kilogram(2).checked_sub(Kilogram(5)), None);
+        assert_eq!(Kilogram(5).checked_sub(Kilogram(2)), Some(Kilogram(3)));
+        assert_eq!(
+            [Kilogram(2), Kilogram(3)].iter().sum::<Kilogram>(),
+            Kilogram(5)
+        );
+        assert_eq!(Kilogram::from_tonnes(2), Kilogram(2000));
+        assert_eq!(Kilogram(1500).as_tonnes(), 1.5);
+        assert_eq!(Kilogram(5).to_string(), "5kg");
+
+        assert_eq!("5".parse(), Ok(Kilogram(5)));
+        assert_eq!("5kg".parse(), Ok(Kilogram(5)));
+        assert_eq!("2T".parse(), Ok(Kilogram(2000)));
+        assert_eq!("2t500kg".parse(), Ok(Kilogram(2500)));
+        for text in ["", "kg", "5g", "-5", "5kg2", "5000000t"] {
+            assert!(text.parse::<Kilogram>().is_err(), "{text}");
+        }
     }
 }
@@ -1,20 +1,144 @@
-use std::ops::{Add, Sub};
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{Add, Sub},
+    str::FromStr,
+};
 
-#[derive(Debug, Default, Clone, PartialEq, PartialOrd, Ord, Eq)]
+use serde::{Deserialize, Serialize};
+
+use super::{parse_quantity, ParseQuantityError};
+
+pub const MINUTES_PER_HOUR: u32 = 60;
+pub const MINUTES_PER_DAY: u32 = 24 * MINUTES_PER_HOUR;
+
+#[derive(Debug, Default, Clone, PartialEq, PartialOrd, Ord, Eq, Serialize, Deserialize)]
 pub struct Minute(pub u32);
 
+impl Minute {
+    pub fn from_hours(hours: u32) -> Self {
+        Self(hours.saturating_mul(MINUTES_PER_HOUR))
+    }
+
+    pub fn from_days(days: u32) -> Self {
+        Self(days.saturating_mul(MINUTES_PER_DAY))
+    }
+
+    pub fn as_hours(&self) -> f64 {
+        f64::from(self.0) / f64::from(MINUTES_PER_HOUR)
+    }
+
+    pub fn as_days(&self) -> f64 {
+        f64::from(self.0) / f64::from(MINUTES_PER_DAY)
+    }
+
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Self)
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Self)
+    }
+
+    pub fn saturating_add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+}
+
 impl Add for Minute {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        Self(self.0 + rhs.0)
+        self.saturating_add(rhs)
     }
 }
 
+// Saturating, a time already past counts as no time left instead of wrapping
+// around
 impl Sub for Minute {
     type Output = Self;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        Self(self.0 - rhs.0)
+        self.saturating_sub(rhs)
+    }
+}
+
+impl Sum for Minute {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Minute> for Minute {
+    fn sum<I: Iterator<Item = &'a Minute>>(iter: I) -> Self {
+        iter.cloned().sum()
+    }
+}
+
+/// Days, hours and minutes leaving out the empty ones, like `1d2h` or `1h30m`.
+impl fmt::Display for Minute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days = self.0 / MINUTES_PER_DAY;
+        let hours = self.0 % MINUTES_PER_DAY / MINUTES_PER_HOUR;
+        let minutes = self.0 % MINUTES_PER_HOUR;
+        if days > 0 {
+            write!(f, "{days}d")?;
+        }
+        if hours > 0 {
+            write!(f, "{hours}h")?;
+        }
+        if minutes > 0 || self.0 == 0 {
+            write!(f, "{minutes}m")?;
+        }
+        Ok(())
+    }
+}
+
+/// Minutes from `90`, `90m`, `90min`, `1h30m` or `2d`.
+impl FromStr for Minute {
+    type Err = ParseQuantityError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let units = [
+            ("m", 1),
+            ("min", 1),
+            ("h", MINUTES_PER_HOUR),
+            ("d", MINUTES_PER_DAY),
+        ];
+        parse_quantity(text, &units).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minute() {
+        assert_eq!(Minute(10) - Minute(30), Minute(0));
+        assert_eq!(Minute(u32::MAX) + Minute(1), Minute(u32::MAX));
+        assert_eq!(Minute(u32::MAX).checked_add(Minute(1)), None);
+        assert_eq!(
+            vec![Minute(10), Minute(20)].into_iter().sum::<Minute>(),
+            Minute(30)
+        );
+        assert_eq!(Minute::from_hours(2), Minute(120));
+        assert_eq!(Minute::from_days(1), Minute(1440));
+        assert_eq!(Minute(90).as_hours(), 1.5);
+        assert_eq!(Minute(0).to_string(), "0m");
+        assert_eq!(Minute(90).to_string(), "1h30m");
+        assert_eq!(Minute(1560).to_string(), "1d2h");
+
+        assert_eq!("90".parse(), Ok(Minute(90)));
+        assert_eq!("1H30M".parse(), Ok(Minute(90)));
+        assert_eq!("1h 30min".parse(), Ok(Minute(90)));
+        assert_eq!("2d".parse(), Ok(Minute(2880)));
+        for text in ["", "h", "1h30", "1s", "1.5h"] {
+            assert!(text.parse::<Minute>().is_err(), "{text}");
+        }
     }
 }
@@ -0,0 +1,95 @@
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{Add, Sub},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{parse_quantity, ParseQuantityError};
+
+pub const LITERS_PER_CUBIC_METER: u32 = 1000;
+
+#[derive(Debug, Default, Clone, PartialOrd, PartialEq, Serialize, Deserialize)]
+pub struct Liter(pub u32);
+
+impl Liter {
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.0.checked_add(rhs.0).map(Self)
+    }
+
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Self)
+    }
+
+    pub fn saturating_add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+}
+
+// Saturating like `Kilogram`
+impl Sub for Liter {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.saturating_sub(rhs)
+    }
+}
+
+impl Add for Liter {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        self.saturating_add(rhs)
+    }
+}
+
+impl Sum for Liter {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+impl fmt::Display for Liter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}l", self.0)
+    }
+}
+
+/// Liters from `300`, `300l` or `2m3`.
+impl FromStr for Liter {
+    type Err = ParseQuantityError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        parse_quantity(text, &[("l", 1), ("m3", LITERS_PER_CUBIC_METER)]).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_liter() {
+        assert_eq!(Liter(2) - Liter(5), Liter(0));
+        assert_eq!(Liter(u32::MAX) + Liter(1), Liter(u32::MAX));
+        assert_eq!(Liter(u32::MAX).checked_add(Liter(1)), None);
+        assert_eq!(Liter(2).checked_sub(Liter(5)), None);
+        assert_eq!(Liter(5).checked_sub(Liter(2)), Some(Liter(3)));
+        assert_eq!([Liter(2), Liter(3)].into_iter().sum::<Liter>(), Liter(5));
+        assert_eq!(Liter(300).to_string(), "300l");
+
+        assert_eq!("300".parse(), Ok(Liter(300)));
+        assert_eq!("300l".parse(), Ok(Liter(300)));
+        assert_eq!("2M3".parse(), Ok(Liter(2000)));
+        assert_eq!("2m3500l".parse(), Ok(Liter(2500)));
+        for text in ["", "l", "5ml", "-5", "5l2", "5000000m3"] {
+            assert!(text.parse::<Liter>().is_err(), "{text}");
+        }
+    }
+}